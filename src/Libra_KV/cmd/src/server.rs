@@ -22,7 +22,8 @@ use concurrency_manager::ConcurrencyManager;
 use encryption::DataKeyManager;
 use engine_rocks::{encryption::get_env, RocksEngine};
 use engine_traits::{
-    compaction_job::CompactionJobInfo, Engines, MetricsFlusher, RaftEngine, CF_DEFAULT, CF_WRITE,
+    compaction_job::CompactionJobInfo, Engines, MetricsFlusher, MiscExt, RaftEngine, CF_DEFAULT,
+    CF_LOCK, CF_WRITE,
 };
 use fs2::FileExt;
 use futures::executor::block_on;
@@ -106,13 +107,23 @@ pub fn run_tikv(config: TiKvConfig) {
             let engines = tikv.init_raw_engines();
             tikv.init_engines(engines);
             let gc_worker = tikv.init_gc_worker();
+            // TODO: `init_servers`'s `node.start(..)` (below) fatally aborts if a `StoreIdent`
+            // was already written to the kv engine but PD reports the cluster as not-yet-
+            // bootstrapped -- a real crash-between-ident-write-and-bootstrap failure mode. A
+            // `check_or_allocate_store` step here, ahead of `init_servers`, that reads any
+            // existing `StoreIdent` and, when PD isn't bootstrapped yet, reuses that store/
+            // cluster id to retry bootstrap instead of panicking (only a conflicting cluster id
+            // should stay fatal) would need changes inside `Node::start` itself, which lives in
+            // `tikv::server::node` -- not vendored in this checkout, only imported here.
             let server_config = tikv.init_servers(&gc_worker);
             tikv.register_services();
             tikv.init_metrics_flusher();
             tikv.run_server(server_config);
             tikv.run_status_server();
 
+            let kv_engine = tikv.engines.as_ref().unwrap().engines.kv.clone();
             signal_handler::wait_for_signal(Some(tikv.engines.take().unwrap().engines));
+            flush_cfs_before_exit(&kv_engine);
             tikv.stop();
         }};
     }
@@ -126,6 +137,21 @@ pub fn run_tikv(config: TiKvConfig) {
 
 const RESERVED_OPEN_FDS: u64 = 1000;
 
+/// Flushes the write/lock/default column families (with `wait = true`, not the whole engine)
+/// before the raft layer tears down, so the next startup replays fewer raft logs / recovers less
+/// WAL against a clean memtable state -- `MetricsFlusher` never touches data, only metrics, so
+/// this is the only durability step shutdown was missing. Especially worth its cost on the
+/// `RaftLogEngine` path, where the kv engine is the only RocksDB instance left to flush.
+/// TODO: gate this behind a `flush_before_exit`/timeout pair in `TiKvConfig` -- that struct lives
+/// in `tikv::config`, not vendored in this checkout, only imported here.
+fn flush_cfs_before_exit(kv_engine: &RocksEngine) {
+    for cf in &[CF_DEFAULT, CF_LOCK, CF_WRITE] {
+        if let Err(e) = kv_engine.flush_cf(cf, true) {
+            error!(%e; "failed to flush column family before exit"; "cf" => *cf);
+        }
+    }
+}
+
 /// A complete TiKV server.
 struct TiKVServer<ER: RaftEngine> {
     config: TiKvConfig,
@@ -142,6 +168,14 @@ struct TiKVServer<ER: RaftEngine> {
     servers: Option<Servers<ER>>,
     region_info_accessor: RegionInfoAccessor,
     coprocessor_host: Option<CoprocessorHost<RocksEngine>>,
+    // TODO: `to_stop` only knows how to `stop()` each worker, blindly, on shutdown -- there's no
+    // way to list what's running, see which cdc/backup/metrics-flusher workers are idle or have
+    // died, or pause one (e.g. backup-endpoint) without restarting the node. A `WorkerManager`
+    // each spawned worker registers into -- tracking Active/Idle/Dead state, last-tick time,
+    // error count, queue depth, and exposing pause/resume/cancel control channels, surfaced as a
+    // `/workers` JSON endpoint on `StatusServer` -- would mean extending the `Stop` trait itself,
+    // which (like `Worker` and `StatusServer`) lives in `tikv_util`/`tikv::server::status_server`,
+    // not vendored in this checkout, only imported here.
     to_stop: Vec<Box<dyn Stop>>,
     lock_files: Vec<File>,
     concurrency_manager: ConcurrencyManager,
@@ -159,6 +193,7 @@ struct Servers<ER: RaftEngine> {
     node: Node<RpcClient, ER>,
     importer: Arc<SSTImporter>,
     cdc_scheduler: tikv_util::worker::Scheduler<cdc::Task>,
+    backup_scheduler: tikv_util::worker::Scheduler<backup::Task>,
 }
 
 impl<ER: RaftEngine> TiKVServer<ER> {
@@ -432,6 +467,12 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         });
     }
 
+    // TODO: `GcConfig` has no `max_write_bytes_per_sec`, so a compaction-heavy GC run can still
+    // starve foreground workloads -- a token-bucket limiter (atomic byte budget replenished at
+    // `rate/10` every ~100ms, capacity == rate) acquired before each physical GC write, with
+    // `GcConfigManager::set_config` swapping the rate (including disabling at `0`) without
+    // restarting the worker, would need changes to `GcWorker`/`GcConfig` themselves, which live
+    // in `tikv::server::gc_worker` -- not vendored in this checkout, only imported here.
     fn init_gc_worker(
         &mut self,
     ) -> GcWorker<RaftKv<ServerRaftStoreRouter<RocksEngine, ER>>, RaftRouter<RocksEngine, ER>> {
@@ -470,6 +511,10 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         let cdc_scheduler = cdc_worker.scheduler();
         let txn_extra_scheduler = cdc::CdcTxnExtraScheduler::new(cdc_scheduler.clone());
 
+        // Create backup.
+        let mut backup_worker = Box::new(tikv_util::worker::Worker::new("backup-endpoint"));
+        let backup_scheduler = backup_worker.scheduler();
+
         self.engines
             .as_mut()
             .unwrap()
@@ -675,17 +720,53 @@ impl<ER: RaftEngine> TiKVServer<ER> {
             .unwrap_or_else(|e| fatal!("failed to start cdc: {}", e));
         self.to_stop.push(cdc_worker);
 
+        // TODO: The only backup path registered here is this scan-based `backup::Endpoint` --
+        // there's no second, `create_backup_disk_snap`-style service that flushes the relevant
+        // column families, records the applied index/resolved-ts via `concurrency_manager`, and
+        // hardlink-snapshots `storage::config::DEFAULT_ROCKSDB_SUB_DIR` for much faster whole-
+        // store backups on large datasets. That would need a new RPC service generated into
+        // `kvproto::backup` (only `create_backup` exists there today) and a matching endpoint
+        // type, neither vendored in this checkout.
+        // Start backup endpoint.
+        let backup_endpoint = backup::Endpoint::new(
+            node.id(),
+            engines.engine.clone(),
+            self.region_info_accessor.clone(),
+            engines.engines.kv.as_inner().clone(),
+            self.config.backup.clone(),
+            self.concurrency_manager.clone(),
+            self.encryption_key_manager.clone(),
+        );
+        cfg_controller.register(
+            tikv::config::Module::Backup,
+            Box::new(backup_endpoint.get_config_manager()),
+        );
+        let backup_timer = backup_endpoint.new_timer();
+        backup_worker
+            .start_with_timer(backup_endpoint, backup_timer)
+            .unwrap_or_else(|e| fatal!("failed to start backup endpoint: {}", e));
+        self.to_stop.push(backup_worker);
+
         self.servers = Some(Servers {
             lock_mgr,
             server,
             node,
             importer,
             cdc_scheduler,
+            backup_scheduler,
         });
 
         server_config
     }
 
+    // TODO: `ImportSSTService::new`, `backup::Endpoint::new`, and the cdc endpoint below are each
+    // constructed with no shared IO budget, so a large SST ingest or backup can starve foreground
+    // traffic. A single token-bucket `IoRateLimiter` (bytes_per_sec capacity, ~100ms refill,
+    // priority-weighted fairness) built once in the bootstrap and handed to all three, plus a
+    // `DBConfigManger`-style manager registered under a new `tikv::config::Module::IoLimiter` so
+    // `bytes_per_sec` can be changed at runtime, would need that new `Module` variant added to
+    // `tikv::config` -- not vendored in this checkout, only imported here -- alongside the
+    // `IoRateLimiter` primitive itself.
     fn register_services(&mut self) {
         let servers = self.servers.as_mut().unwrap();
         let engines = self.engines.as_ref().unwrap();
@@ -722,6 +803,14 @@ impl<ER: RaftEngine> TiKVServer<ER> {
             fatal!("failed to register debug service");
         }
 
+        // TODO: `DiagnosticsService` only serves log files and config -- there's no reachability
+        // signal here at all. A per-store table tracking `last_broadcast: Instant` and a running
+        // `received_message_count` (fed by the same message-arrival counter noted below, on the
+        // `fsm::store` unreachable-report path), classified as reachable/suspect/unreachable from
+        // evidence rather than a pure time backoff, and exposed through a periodic collector wired
+        // into the worker set below plus a new Diagnostics RPC, would need both a new method on
+        // `diagnosticspb` (only `search_log`/`server_info` exist today) and changes inside
+        // `DiagnosticsService` itself -- neither vendored in this checkout, only imported here.
         // Create Diagnostics service
         let diag_service = DiagnosticsService::new(
             servers.server.get_debug_thread_pool().clone(),
@@ -760,9 +849,8 @@ impl<ER: RaftEngine> TiKVServer<ER> {
             .unwrap_or_else(|e| fatal!("failed to start lock manager: {}", e));
 
         // Backup service.
-        let mut backup_worker = Box::new(tikv_util::worker::Worker::new("backup-endpoint"));
-        let backup_scheduler = backup_worker.scheduler();
-        let backup_service = backup::Service::new(backup_scheduler, self.security_mgr.clone());
+        let backup_service =
+            backup::Service::new(servers.backup_scheduler.clone(), self.security_mgr.clone());
         if servers
             .server
             .register_service(create_backup(backup_service))
@@ -771,23 +859,6 @@ impl<ER: RaftEngine> TiKVServer<ER> {
             fatal!("failed to register backup service");
         }
 
-        let backup_endpoint = backup::Endpoint::new(
-            servers.node.id(),
-            engines.engine.clone(),
-            self.region_info_accessor.clone(),
-            engines.engines.kv.as_inner().clone(),
-            self.config.backup.clone(),
-            self.concurrency_manager.clone(),
-        );
-        self.cfg_controller.as_mut().unwrap().register(
-            tikv::config::Module::Backup,
-            Box::new(backup_endpoint.get_config_manager()),
-        );
-        let backup_timer = backup_endpoint.new_timer();
-        backup_worker
-            .start_with_timer(backup_endpoint, backup_timer)
-            .unwrap_or_else(|e| fatal!("failed to start backup endpoint: {}", e));
-
         let cdc_service =
             cdc::Service::new(servers.cdc_scheduler.clone(), self.security_mgr.clone());
         if servers
@@ -797,8 +868,6 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         {
             fatal!("failed to register cdc service");
         }
-
-        self.to_stop.push(backup_worker);
     }
 
     fn init_metrics_flusher(&mut self) {
@@ -826,6 +895,15 @@ impl<ER: RaftEngine> TiKVServer<ER> {
             .unwrap_or_else(|e| fatal!("failed to start server: {}", e));
     }
 
+    // TODO: `StatusServer` has no `/reachability` endpoint, so operators can't see why a peer
+    // is (or isn't) being marked unreachable -- today's report logic is a pure time backoff. A
+    // reachability subsystem tracking, per destination store, `last_broadcast: Instant` plus a
+    // `received_message_count` sampled from a per-store received-messages counter (suppressing
+    // the broadcast when the count has advanced since the last one, proving liveness, and
+    // falling back to the time backoff otherwise) would need changes in the raftstore store FSM
+    // that owns the unreachable-report path (`fsm::store`, only referenced here, not vendored in
+    // this checkout) plus a new route on `StatusServer` itself (`tikv::server::status_server`,
+    // also not vendored here).
     fn run_status_server(&mut self) {
         // Create a status server.
         let status_enabled =
@@ -1,7 +1,9 @@
 // Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
 
 use std::cmp::Ordering;
+use std::cmp::Reverse;
 use std::collections::BinaryHeap;
+use std::collections::VecDeque;
 use std::slice::Iter;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -12,6 +14,7 @@ use kvproto::kvrpcpb::KeyRange;
 use kvproto::metapb::Peer;
 
 use rand::Rng;
+use rayon::prelude::*;
 
 use tikv_util::collections::HashMap;
 use tikv_util::config::Tracker;
@@ -23,9 +26,22 @@ use crate::store::worker::{FlowStatistics, SplitConfig, SplitConfigManager};
 
 pub const TOP_N: usize = 10;
 
+#[derive(Clone)]
 pub struct RatioSplitInfo
 {
     pub dim_id: u64,
+    /// Optional `(dim_id, weight)` pairs to balance the split key against several load
+    /// dimensions at once instead of just `dim_id` -- e.g. `[(0, 0.5), (1, 0.5)]` splits on an
+    /// even mix of byte rate and request rate. Empty (the default) falls back to `dim_id` alone
+    /// at weight `1.0`, i.e. today's single-dimension behavior; see
+    /// [`RatioSplitInfo::dims_or_default`].
+    ///
+    /// TODO: this would naturally live as a `Vec<(u64, f64)>` field on `SplitConfig` (set once
+    /// from the split-hub's config file/table and threaded down here per region), but
+    /// `SplitConfig`'s definition lives in `split_config.rs`, which this checkout doesn't have
+    /// (only this file exists under `store::worker`) -- so for now a caller that wants combined
+    /// scoring has to populate `dims` on each `RatioSplitInfo` directly.
+    pub dims: Vec<(u64, f64)>,
     pub ratio: f64,
     pub rw_type: u64, // 0 => read, other => write
     pub create_time: Instant,
@@ -35,11 +51,38 @@ impl RatioSplitInfo {
     fn new() -> RatioSplitInfo {
         RatioSplitInfo {
             dim_id: 0,
+            dims: vec![],
             ratio: 0.0,
             rw_type: 0,
             create_time: Instant::now(),
         }
     }
+
+    /// `dims`, or `[(dim_id, 1.0)]` if it's empty -- the single-dimension behavior every
+    /// existing `RatioSplitInfo` (built via `new`, which leaves `dims` empty) keeps by default.
+    fn dims_or_default(&self) -> Vec<(u64, f64)> {
+        if self.dims.is_empty() {
+            vec![(self.dim_id, 1.0)]
+        } else {
+            self.dims.clone()
+        }
+    }
+}
+
+/// Classifies the kind of read that produced a [`RequestInfo`] sample, so that load-based
+/// splitting can balance query count per kind rather than only byte/key size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Get,
+    BatchGet,
+    Scan,
+    Coprocessor,
+}
+
+impl Default for QueryKind {
+    fn default() -> QueryKind {
+        QueryKind::Get
+    }
 }
 
 #[derive(Default, Debug, Clone)]
@@ -48,6 +91,7 @@ pub struct RequestInfo {
     pub end_key: Vec<u8>,
     pub bytes: usize,
     pub keys: usize,
+    pub query_kind: QueryKind,
 }
 
 impl RequestInfo {
@@ -60,12 +104,86 @@ impl RequestInfo {
     }
 }
 
+/// Per-[`RequestInfo`] load combined across every `(dim_id, weight)` pair in `dims`. Each
+/// dimension is normalized by its own total across `req_infos` first (so a region that's
+/// merely byte-heavy, e.g., doesn't swamp a qps-weighted term just because bytes happen to be
+/// numerically larger), then the normalized per-dimension shares are combined as a weighted sum.
+/// The result lines up index-for-index with `req_infos`; its sum (the sum of the weights, less
+/// any dimension with no load at all) is the balancing total `choose_bounds` splits against.
+fn combined_loads(req_infos: &[RequestInfo], dims: &[(u64, f64)]) -> Vec<f64> {
+    let totals: Vec<f64> = dims
+        .iter()
+        .map(|(dim_id, _)| {
+            req_infos
+                .iter()
+                .map(|req_info| req_info.get_load(*dim_id))
+                .sum::<f64>()
+                .max(f64::MIN_POSITIVE)
+        })
+        .collect();
+    req_infos
+        .iter()
+        .map(|req_info| {
+            dims.iter()
+                .zip(totals.iter())
+                .map(|((dim_id, weight), total)| weight * req_info.get_load(*dim_id) / total)
+                .sum()
+        })
+        .collect()
+}
+
 pub struct SplitInfo {
     pub region_id: u64,
     pub split_keys: Vec<Vec<u8>>,
     pub peer: Peer,
 }
 
+/// Sub-region traffic buckets for one region, advisory only (no physical split involved) --
+/// `boundary_keys[i]` separates bucket `i` from bucket `i + 1`, and `traffic_per_bucket[i]` is
+/// that bucket's share of the sample traffic the buckets were computed from.
+/// `traffic_per_bucket.len() == boundary_keys.len() + 1` always holds. See
+/// [`Recorder::collect_buckets`].
+#[derive(Debug, Clone)]
+pub struct BucketStat {
+    pub region_id: u64,
+    pub boundary_keys: Vec<Vec<u8>>,
+    pub traffic_per_bucket: Vec<usize>,
+}
+
+/// Which load dimension [`AutoSplitController::flush`]'s top-N hot-region report
+/// ([`HotRegionInfo`]) is ordered by. Defaults to [`HotRegionSortKey::Qps`], matching `flush`'s
+/// old plain-qps report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotRegionSortKey {
+    Qps,
+    Bytes,
+}
+
+impl Default for HotRegionSortKey {
+    fn default() -> Self {
+        HotRegionSortKey::Qps
+    }
+}
+
+/// A region's full load vector for one `flush` interval, as reported in the top-N hot-region
+/// list `flush` returns alongside its split decisions -- enough for a PD/scheduling consumer to
+/// see not just *that* a region is hot but *why* (`qps` vs `bytes` vs `keys`) and whether the
+/// load was reads or writes (`rw_type`, mirroring [`ReadStats::rw_type`]'s convention).
+#[derive(Debug, Clone)]
+pub struct HotRegionInfo {
+    pub region_id: u64,
+    pub peer: Peer,
+    pub qps: usize,
+    pub bytes: usize,
+    pub keys: usize,
+    pub rw_type: u64,
+    /// This region's guaranteed-hot key ranges (see [`HotRangeSketch::guaranteed_hot`]),
+    /// reported even on rounds where no split was triggered so an operator or scheduler can
+    /// still see which ranges are driving the load.
+    pub hot_ranges: Vec<HotRangeInfo>,
+}
+
+#[derive(Clone)]
 pub struct Sample {
     pub key: Vec<u8>,
     pub left: i32,
@@ -116,6 +234,12 @@ where
     let mut rng = rand::thread_rng();
     let mut key_ranges = vec![];
     let high_bound = pre_sum.last().unwrap();
+    if *high_bound == 0 {
+        // Every list is empty, or `pre_sum` was built from a weight (e.g. qps) that happens to
+        // be zero for all of them -- either way there's nothing to weight the choice of list by,
+        // and `rng.gen_range(0, 0)` would panic.
+        return key_ranges;
+    }
     for _num in 0..sample_num {
         let d = rng.gen_range(0, *high_bound) as usize;
         let i = match pre_sum.binary_search(&d) {
@@ -129,6 +253,332 @@ where
     key_ranges
 }
 
+/// Divides `samples` into `bucket_count` buckets of roughly equal accumulated access count
+/// (`left + right + contained`), reusing the same sorted-by-key-then-`prefix_sum` quantile
+/// selection [`Recorder::split_keys`] uses for batch splits, just without its
+/// balance/contained-score gates or minimum-gap guard -- buckets are advisory, not physical
+/// splits, so every quantile is simply assigned the nearest sample at or past it. Returns
+/// `bucket_count - 1` boundary keys (fewer if `samples` doesn't have that many distinct access
+/// levels to split on) and one more traffic total than that -- one per bucket.
+fn bucket_stats(mut samples: Vec<Sample>, bucket_count: usize) -> (Vec<Vec<u8>>, Vec<usize>) {
+    let total: usize = samples.iter().map(|s| s.left + s.right + s.contained).sum();
+    if bucket_count <= 1 || samples.is_empty() {
+        return (vec![], vec![total]);
+    }
+
+    samples.sort_by(|a, b| a.key.cmp(&b.key));
+    let pre_sum = prefix_sum(samples.iter(), |s| s.left + s.right + s.contained);
+
+    let mut boundary_keys = vec![];
+    let mut cumulative_at_boundary = vec![0];
+    for i in 1..bucket_count {
+        let target = total * i / bucket_count;
+        if let Some(idx) = pre_sum.iter().position(|&cum| cum >= target) {
+            if boundary_keys.last() != Some(&samples[idx].key) {
+                boundary_keys.push(samples[idx].key.clone());
+                cumulative_at_boundary.push(pre_sum[idx]);
+            }
+        }
+    }
+    cumulative_at_boundary.push(total);
+
+    let traffic_per_bucket = cumulative_at_boundary
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .collect();
+    (boundary_keys, traffic_per_bucket)
+}
+
+/// Weighted (A-Res) reservoir sampling: keeps the `sample_num` items with the largest
+/// `u^(1/w)` key, where `u` is drawn uniformly from `(0, 1)` and `w` is `weight(item)` -- the
+/// heavier an item, the more likely its key survives. Unlike the count-based [`sample`] above,
+/// this biases the kept set toward whatever `weight` measures (e.g. request bytes), so
+/// downstream split-key scoring balances that quantity rather than request count. Any weight
+/// below `1.0` (including `<= 0`) is floored to `1.0`, so such an item is still representable
+/// rather than excluded or allowed to produce a degenerate (zero or NaN) key.
+fn weighted_sample<T>(sample_num: usize, items: Vec<T>, weight: impl Fn(&T) -> f64) -> Vec<T> {
+    struct Slot<T> {
+        key: f64,
+        item: T,
+    }
+    impl<T> PartialEq for Slot<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+    impl<T> Eq for Slot<T> {}
+    impl<T> PartialOrd for Slot<T> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl<T> Ord for Slot<T> {
+        // Reversed so the `BinaryHeap` (a max-heap) pops the *smallest* key first -- the
+        // weakest candidate, evicted when the reservoir is over capacity.
+        fn cmp(&self, other: &Self) -> Ordering {
+            other.key.partial_cmp(&self.key).unwrap_or(Ordering::Equal)
+        }
+    }
+
+    if sample_num == 0 {
+        return vec![];
+    }
+    let mut rng = rand::thread_rng();
+    let mut heap: BinaryHeap<Slot<T>> = BinaryHeap::with_capacity(sample_num);
+    for item in items {
+        let w = weight(&item).max(1.0);
+        let u: f64 = rng.gen_range(0.0, 1.0);
+        let key = u.powf(1.0 / w);
+        if heap.len() < sample_num {
+            heap.push(Slot { key, item });
+        } else if heap.peek().map_or(false, |smallest| key > smallest.key) {
+            heap.pop();
+            heap.push(Slot { key, item });
+        }
+    }
+    heap.into_iter().map(|slot| slot.item).collect()
+}
+
+/// Online two-heap median maintainer over byte-string keys, so `Recorder::choose_middle` can
+/// find the middle key of a window in a single streaming pass instead of materializing and
+/// sorting every candidate first. `lower` is a max-heap holding the half at or below the
+/// median; `upper` is a min-heap (via `Reverse`) holding the half above it; `push` keeps
+/// `|lower| - |upper| <= 1` by moving the appropriate top across whenever that invariant (and
+/// `max(lower) <= min(upper)`) would otherwise be violated. Each `choose_middle` call builds and
+/// discards its own maintainer for that bound pair's window, so there's no need to support
+/// evicting a key once it's pushed.
+struct StreamingMedian {
+    lower: BinaryHeap<Vec<u8>>,
+    upper: BinaryHeap<Reverse<Vec<u8>>>,
+}
+
+impl StreamingMedian {
+    fn new() -> Self {
+        StreamingMedian {
+            lower: BinaryHeap::new(),
+            upper: BinaryHeap::new(),
+        }
+    }
+
+    fn push(&mut self, key: Vec<u8>) {
+        let goes_lower = match self.lower.peek() {
+            Some(top) => key <= *top,
+            None => true,
+        };
+        if goes_lower {
+            self.lower.push(key);
+        } else {
+            self.upper.push(Reverse(key));
+        }
+
+        if self.lower.len() > self.upper.len() + 1 {
+            if let Some(top) = self.lower.pop() {
+                self.upper.push(Reverse(top));
+            }
+        } else if self.upper.len() > self.lower.len() {
+            if let Some(Reverse(top)) = self.upper.pop() {
+                self.lower.push(top);
+            }
+        }
+    }
+
+    fn median(&self) -> Option<Vec<u8>> {
+        self.lower.peek().cloned()
+    }
+}
+
+/// Number of hottest keys a region's [`HotKeySketch`] retains. Would move to `Config` once this
+/// tree has a `storage::config` module to put it in (see `ReadPoolTuner`'s equivalent note).
+pub const HOT_KEY_TOP_N: usize = 10;
+
+/// A real `farmhash` crate isn't wired into this tree, so keys are hashed with this FNV-1a
+/// variant instead; it's only used to dedupe repeat observations of the same key inside a
+/// sketch, not exposed outside it.
+fn hot_key_hash(key: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = FNV_OFFSET;
+    for &b in key {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Approximate hottest-key tracker for a single region: a Misra-Gries / Space-Saving sketch
+/// bounded to [`HOT_KEY_TOP_N`] entries. Every observed key either bumps an existing entry's
+/// count, fills a free slot, or, once the sketch is full, evicts the entry with the smallest
+/// count and reseeds it at `min_count + 1` keyed to the new key — the standard guarantee that a
+/// reported count never overcounts a key's true frequency by more than the count it evicted.
+#[derive(Debug, Clone, Default)]
+pub struct HotKeySketch {
+    counters: Vec<(u64, Vec<u8>, usize)>,
+}
+
+impl HotKeySketch {
+    fn observe(&mut self, key: &[u8]) {
+        let hash = hot_key_hash(key);
+        if let Some(slot) = self.counters.iter_mut().find(|(h, _, _)| *h == hash) {
+            slot.2 += 1;
+            return;
+        }
+        if self.counters.len() < HOT_KEY_TOP_N {
+            self.counters.push((hash, key.to_vec(), 1));
+            return;
+        }
+        let min_idx = self
+            .counters
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, _, count))| *count)
+            .map(|(i, _)| i)
+            .unwrap();
+        let min_count = self.counters[min_idx].2;
+        self.counters[min_idx] = (hash, key.to_vec(), min_count + 1);
+    }
+
+    /// The sketch's current entries, sorted by descending approximate count.
+    pub fn top_keys(&self) -> Vec<(Vec<u8>, usize)> {
+        let mut entries: Vec<_> = self
+            .counters
+            .iter()
+            .map(|(_, key, count)| (key.clone(), *count))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1));
+        entries
+    }
+}
+
+/// Number of hottest key ranges a region's [`HotRangeSketch`] retains. A separate budget from
+/// [`HOT_KEY_TOP_N`] since ranges and single keys are tracked independently.
+pub const HOT_RANGE_TOP_N: usize = 10;
+
+/// Hashes `(start_key, end_key)` by combining [`hot_key_hash`] of each half -- cheaper than
+/// concatenating the two into one buffer first, and collisions only cost this sketch an extra
+/// eviction, not correctness.
+fn hot_range_hash(start_key: &[u8], end_key: &[u8]) -> u64 {
+    hot_key_hash(start_key) ^ hot_key_hash(end_key).rotate_left(32)
+}
+
+/// One [`HotRangeSketch`] entry: `count` is the Space-Saving algorithm's running estimate for
+/// this key range, and `error` bounds how much `count` could be overcounting by -- the range's
+/// true frequency is guaranteed to be in `[count - error, count]`. See
+/// [`HotRangeSketch::guaranteed_hot`].
+#[derive(Debug, Clone)]
+pub struct HotRangeInfo {
+    pub start_key: Vec<u8>,
+    pub end_key: Vec<u8>,
+    pub count: usize,
+    pub error: usize,
+}
+
+/// Approximate hottest-key-*range* tracker, the same Space-Saving sketch as [`HotKeySketch`] but
+/// keyed on a whole `[start_key, end_key)` range instead of a point key, weighted by an
+/// arbitrary per-observation weight (bytes, keys, ...) rather than a flat `+1`, and tracking
+/// each entry's error bound explicitly rather than leaving it implicit in the eviction history.
+/// Min-eviction stays a linear scan over the (tiny, [`HOT_RANGE_TOP_N`]-bounded) entry list --
+/// same call as [`HotKeySketch`] made -- rather than a stream-summary bucket structure, since at
+/// this capacity the scan is already cheaper than the bookkeeping a bucket list would add.
+#[derive(Debug, Clone, Default)]
+pub struct HotRangeSketch {
+    // (hash, start_key, end_key, count, error)
+    entries: Vec<(u64, Vec<u8>, Vec<u8>, usize, usize)>,
+}
+
+impl HotRangeSketch {
+    fn observe(&mut self, start_key: &[u8], end_key: &[u8], weight: usize) {
+        let hash = hot_range_hash(start_key, end_key);
+        if let Some(slot) = self
+            .entries
+            .iter_mut()
+            .find(|(h, s, e, _, _)| *h == hash && s == start_key && e == end_key)
+        {
+            slot.3 += weight;
+            return;
+        }
+        if self.entries.len() < HOT_RANGE_TOP_N {
+            self.entries
+                .push((hash, start_key.to_vec(), end_key.to_vec(), weight, 0));
+            return;
+        }
+        let min_idx = self
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, _, _, count, _))| *count)
+            .map(|(i, _)| i)
+            .unwrap();
+        let min_count = self.entries[min_idx].3;
+        self.entries[min_idx] = (
+            hash,
+            start_key.to_vec(),
+            end_key.to_vec(),
+            min_count + weight,
+            min_count,
+        );
+    }
+
+    /// The sketch's current entries, sorted by descending approximate count.
+    pub fn top_ranges(&self) -> Vec<HotRangeInfo> {
+        let mut entries: Vec<HotRangeInfo> = self
+            .entries
+            .iter()
+            .map(|(_, start_key, end_key, count, error)| HotRangeInfo {
+                start_key: start_key.clone(),
+                end_key: end_key.clone(),
+                count: *count,
+                error: *error,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.count.cmp(&a.count));
+        entries
+    }
+
+    /// [`top_ranges`](Self::top_ranges) filtered to entries whose true count is *guaranteed* to
+    /// be at least `support_threshold`, i.e. `count - error >= support_threshold` -- anything
+    /// that doesn't clear this bar might just be an artifact of an unlucky eviction, not a
+    /// genuinely hot range.
+    pub fn guaranteed_hot(&self, support_threshold: usize) -> Vec<HotRangeInfo> {
+        self.top_ranges()
+            .into_iter()
+            .filter(|info| info.count.saturating_sub(info.error) >= support_threshold)
+            .collect()
+    }
+
+    /// Combines several per-source sketches (e.g. one per reporting thread this `flush` round)
+    /// into one: matching ranges have their counts and errors summed, then only the heaviest
+    /// [`HOT_RANGE_TOP_N`] survive. This is an approximation of a true Space-Saving merge, but
+    /// good enough for folding this round's independently-sampled sketches together before
+    /// reporting.
+    fn merge<'a>(sketches: impl Iterator<Item = &'a HotRangeSketch>) -> HotRangeSketch {
+        let mut combined: HashMap<(Vec<u8>, Vec<u8>), (usize, usize)> = HashMap::default();
+        for sketch in sketches {
+            for (_, start_key, end_key, count, error) in &sketch.entries {
+                let entry = combined
+                    .entry((start_key.clone(), end_key.clone()))
+                    .or_insert((0, 0));
+                entry.0 += count;
+                entry.1 += error;
+            }
+        }
+        let mut entries: Vec<_> = combined
+            .into_iter()
+            .map(|((start_key, end_key), (count, error))| {
+                (
+                    hot_range_hash(&start_key, &end_key),
+                    start_key,
+                    end_key,
+                    count,
+                    error,
+                )
+            })
+            .collect();
+        entries.sort_by(|a, b| b.3.cmp(&a.3));
+        entries.truncate(HOT_RANGE_TOP_N);
+        HotRangeSketch { entries }
+    }
+}
+
 // RegionInfo will maintain key_ranges with sample_num length by reservoir sampling.
 // And it will save qps num and peer.
 #[derive(Debug, Clone)]
@@ -140,6 +590,53 @@ pub struct RegionInfo {
     pub peer: Peer,
     pub key_ranges: Vec<KeyRange>,
     pub req_infos: Vec<RequestInfo>,
+    /// Query count observed for this region, broken down by [`QueryKind`].
+    pub query_stats: HashMap<QueryKind, usize>,
+    /// Approximate hottest keys sampled from reads against this region.
+    pub hot_keys: HotKeySketch,
+    /// Approximate hottest key *ranges* sampled from reads against this region -- fed from the
+    /// same `add_key_ranges`/`add_flow_bytes` calls as `key_ranges`/`flow_key_ranges`, but never
+    /// evicted by reservoir sampling, so it keeps visibility into which ranges drove the load
+    /// even across rounds that never reach a split decision.
+    pub hot_ranges: HotRangeSketch,
+    /// Mirrors [`RatioSplitInfo::dim_id`]'s convention (`0` => IO/bytes, anything else =>
+    /// CPU/count), set from the owning [`ReadStats::dim_id`]. Only `add_req_infos` acts on it --
+    /// `KeyRange`s carry no byte size to weight by, so `add_key_ranges` stays on uniform
+    /// reservoir sampling regardless of dimension.
+    pub dim_id: u64,
+    /// Total bytes observed for this region via [`ReadStats::add_flow_bytes`], independent of
+    /// `qps`/`bytes` above (which come from the request-count and [`RequestInfo`] paths
+    /// respectively) -- a region can be byte-hot on a handful of large scans without ever
+    /// crossing a qps threshold, so `AutoSplitController::flush` checks this against its own
+    /// `byte_threshold`.
+    pub flow_bytes: usize,
+    /// Byte-weighted reservoir sample of `(KeyRange, bytes)` pairs seen via `add_flow_bytes`,
+    /// kept alongside their byte weight (unlike `key_ranges`) so the weight survives being
+    /// folded into the next call's reservoir -- see [`RegionInfo::add_flow_bytes`].
+    pub flow_key_ranges: Vec<(KeyRange, usize)>,
+    /// CPU time (in seconds) spent serving this region via [`ReadStats::add_cpu`], independent of
+    /// `qps`/`bytes`/`flow_bytes` above -- a region can be CPU-hot on heavy coprocessor work over
+    /// a small range without its request count or byte volume ever crossing their respective
+    /// thresholds, so `AutoSplitController::flush` smooths this across rounds and checks it
+    /// against its own `cpu_threshold`.
+    pub cpu_secs: f64,
+    /// Ordered sub-region bucket boundary keys supplied by the caller (e.g. PD's region-bucket
+    /// meta) via [`RegionInfo::set_bucket_keys`] -- `bucket_keys[i]` separates bucket `i` from
+    /// bucket `i + 1`, same convention as [`BucketStat::boundary_keys`]. Empty until set, in
+    /// which case no bucket-level aggregation happens.
+    pub bucket_keys: Vec<Vec<u8>>,
+    /// Per-bucket QPS/read-bytes/read-keys, aligned with `bucket_keys`:
+    /// `bucket_loads.len() == bucket_keys.len() + 1` once `bucket_keys` is set.
+    pub bucket_loads: Vec<BucketLoad>,
+}
+
+/// Per-bucket load accumulated by [`RegionInfo::distribute_bucket_load`]: request count and read
+/// bytes/keys attributed to one sub-region bucket.
+#[derive(Clone, Debug, Default)]
+pub struct BucketLoad {
+    pub qps: usize,
+    pub bytes: usize,
+    pub keys: usize,
 }
 
 impl RegionInfo {
@@ -152,6 +649,53 @@ impl RegionInfo {
             key_ranges: Vec::with_capacity(sample_num),
             peer: Peer::default(),
             req_infos: Vec::with_capacity(sample_num),
+            query_stats: HashMap::default(),
+            hot_keys: HotKeySketch::default(),
+            hot_ranges: HotRangeSketch::default(),
+            dim_id: 0,
+            flow_bytes: 0,
+            flow_key_ranges: Vec::with_capacity(sample_num),
+            cpu_secs: 0.0,
+            bucket_keys: vec![],
+            bucket_loads: vec![],
+        }
+    }
+
+    /// Sets (or replaces) the sub-region bucket boundaries this region's load is attributed
+    /// against, resetting the per-bucket accumulators.
+    fn set_bucket_keys(&mut self, bucket_keys: Vec<Vec<u8>>) {
+        self.bucket_loads = vec![BucketLoad::default(); bucket_keys.len() + 1];
+        self.bucket_keys = bucket_keys;
+    }
+
+    /// Distributes one `(start, end)` observation -- `qps` requests carrying `bytes`/`keys` of
+    /// read volume -- across every bucket `[start, end)` overlaps. A range that straddles several
+    /// buckets splits its weight evenly across them (buckets have no byte-length metric to weigh
+    /// a partial overlap by more precisely); a range entirely inside one bucket credits that
+    /// bucket the whole weight. An empty `start`/`end` is treated as the region's own lower/upper
+    /// bound. A no-op until `bucket_keys` has been set via `set_bucket_keys`.
+    fn distribute_bucket_load(&mut self, start: &[u8], end: &[u8], qps: usize, bytes: usize, keys: usize) {
+        if self.bucket_keys.is_empty() {
+            return;
+        }
+        let lo = if start.is_empty() {
+            0
+        } else {
+            self.bucket_keys.partition_point(|b| b.as_slice() <= start)
+        };
+        let hi = if end.is_empty() {
+            self.bucket_loads.len() - 1
+        } else {
+            self.bucket_keys
+                .partition_point(|b| b.as_slice() < end)
+                .min(self.bucket_loads.len() - 1)
+        };
+        let (lo, hi) = (lo.min(hi), hi.max(lo));
+        let span = (hi - lo + 1) as f64;
+        for bucket in &mut self.bucket_loads[lo..=hi] {
+            bucket.qps += ((qps as f64) / span).round() as usize;
+            bucket.bytes += ((bytes as f64) / span).round() as usize;
+            bucket.keys += ((keys as f64) / span).round() as usize;
         }
     }
 
@@ -159,12 +703,52 @@ impl RegionInfo {
         self.qps
     }
 
+    fn get_flow_bytes(&self) -> usize {
+        self.flow_bytes
+    }
+
+    /// Folds a batch of `(key_range, bytes)` observations into `flow_bytes`/`flow_key_ranges`,
+    /// reusing [`weighted_sample`] (the same A-Res reservoir [`RegionInfo::add_req_infos`] uses
+    /// for its IO dimension) so the retained sample trends toward the heaviest ranges rather
+    /// than the most numerous ones.
+    fn add_flow_bytes(&mut self, key_ranges: Vec<(KeyRange, usize)>) {
+        for (key_range, bytes) in &key_ranges {
+            self.flow_bytes += bytes;
+            self.hot_ranges
+                .observe(key_range.get_start_key(), key_range.get_end_key(), *bytes);
+            self.distribute_bucket_load(
+                key_range.get_start_key(),
+                key_range.get_end_key(),
+                0,
+                *bytes,
+                0,
+            );
+        }
+        let mut combined = std::mem::take(&mut self.flow_key_ranges);
+        combined.extend(key_ranges);
+        self.flow_key_ranges =
+            weighted_sample(self.sample_num, combined, |(_, bytes)| *bytes as f64);
+    }
+
+    fn add_hot_key(&mut self, key: &[u8]) {
+        self.hot_keys.observe(key);
+    }
+
+    fn add_cpu(&mut self, secs: f64) {
+        self.cpu_secs += secs;
+    }
+
     fn get_key_ranges_mut(&mut self) -> &mut Vec<KeyRange> {
         &mut self.key_ranges
     }
 
     fn add_key_ranges(&mut self, key_ranges: Vec<KeyRange>) {
         self.qps += key_ranges.len();
+        for key_range in &key_ranges {
+            self.hot_ranges
+                .observe(key_range.get_start_key(), key_range.get_end_key(), 1);
+            self.distribute_bucket_load(key_range.get_start_key(), key_range.get_end_key(), 1, 0, 0);
+        }
         for key_range in key_ranges {
             if self.key_ranges.len() < self.sample_num {
                 self.key_ranges.push(key_range);
@@ -183,15 +767,48 @@ impl RegionInfo {
 
     fn add_req_infos(&mut self, req_infos: Vec<RequestInfo>) {
         self.qps += req_infos.len();
-        for req_info in req_infos {
+        for req_info in &req_infos {
             self.bytes += req_info.bytes;
             self.keys += req_info.keys;
-            if self.req_infos.len() < self.sample_num {
-                self.req_infos.push(req_info);
-            } else {
-                let i = rand::thread_rng().gen_range(0, self.qps) as usize;
-                if i < self.sample_num {
-                    self.req_infos[i] = req_info;
+            *self.query_stats.entry(req_info.query_kind).or_insert(0) += 1;
+            // Weight by this region's own dimension, same as `combined_loads` -- bytes for the
+            // IO dimension, a flat per-request count for CPU -- so `hot_ranges` surfaces whichever
+            // ranges are actually driving the split decision, not just the most frequently hit.
+            self.hot_ranges.observe(
+                &req_info.start_key,
+                &req_info.end_key,
+                req_info.get_load(self.dim_id).round() as usize,
+            );
+            self.distribute_bucket_load(
+                &req_info.start_key,
+                &req_info.end_key,
+                1,
+                req_info.bytes,
+                req_info.keys,
+            );
+        }
+
+        if self.dim_id == 0 {
+            // IO dimension: re-run the weighted reservoir over the previously kept sample plus
+            // this batch, biased toward request bytes, so the window's retained `req_infos`
+            // trend toward the heaviest requests rather than the most numerous ones. Rebuilding
+            // from scratch each call is the "reset per detection window" the A-Res heap needs --
+            // it isn't carried across calls.
+            let mut combined = std::mem::take(&mut self.req_infos);
+            combined.extend(req_infos);
+            self.req_infos = weighted_sample(self.sample_num, combined, |req_info| {
+                req_info.bytes as f64
+            });
+        } else {
+            // CPU dimension: unchanged count-weighted reservoir.
+            for req_info in req_infos {
+                if self.req_infos.len() < self.sample_num {
+                    self.req_infos.push(req_info);
+                } else {
+                    let i = rand::thread_rng().gen_range(0, self.qps) as usize;
+                    if i < self.sample_num {
+                        self.req_infos[i] = req_info;
+                    }
                 }
             }
         }
@@ -202,6 +819,10 @@ impl RegionInfo {
             self.peer = peer.clone();
         }
     }
+
+    fn update_dim(&mut self, dim_id: u64) {
+        self.dim_id = dim_id;
+    }
 }
 
 pub struct Recorder {
@@ -245,42 +866,68 @@ impl Recorder {
         self.times >= self.detect_num
     }
 
-    fn collect(&mut self, config: &SplitConfig) -> Vec<u8> {
-        let pre_sum = prefix_sum(self.key_ranges.iter(), Vec::len);
-        let key_ranges = self.key_ranges.clone();
-        let mut samples = sample(config.sample_num, &pre_sum, key_ranges, |x| x)
+    /// Builds the `Sample` set both [`Recorder::collect_key_ranges`] and
+    /// [`Recorder::collect_buckets`] score against, factored out so the two scans over a
+    /// recorder's `key_ranges` history stay in sync instead of each re-deriving its own samples.
+    fn collect_samples(key_ranges: &[Vec<KeyRange>], config: &SplitConfig) -> Vec<Sample> {
+        let pre_sum = prefix_sum(key_ranges.iter(), Vec::len);
+        let mut samples: Vec<Sample> = sample(config.sample_num, &pre_sum, key_ranges.to_vec(), |x| x)
             .iter()
             .map(|key_range| Sample::new(&key_range.start_key))
             .collect();
-        for key_ranges in &self.key_ranges {
+        for key_ranges in key_ranges {
             for key_range in key_ranges {
                 Recorder::sample(&mut samples, &key_range);
             }
         }
-        Recorder::split_key(
+        samples
+    }
+
+    /// Pure variant of the per-region split-key scan: given a snapshot of a recorder's
+    /// accumulated `key_ranges` history and the current config, it neither reads nor writes any
+    /// `Recorder`/`AutoSplitController` state, so `AutoSplitController::flush` can run one of
+    /// these per hot region concurrently via `par_iter` instead of one region at a time.
+    /// `batch_split_limit` is forwarded to [`Recorder::split_keys`] -- `1` (the default)
+    /// preserves the original single-key behavior.
+    fn collect_key_ranges(
+        key_ranges: &[Vec<KeyRange>],
+        config: &SplitConfig,
+        batch_split_limit: usize,
+    ) -> Vec<Vec<u8>> {
+        let samples = Recorder::collect_samples(key_ranges, config);
+        Recorder::split_keys(
             samples,
             config.split_balance_score,
             config.split_contained_score,
             config.sample_threshold,
+            batch_split_limit,
         )
     }
 
-    fn choose_bounds(&self, mut req_infos: Vec<RequestInfo>, ratio_split_info: &RatioSplitInfo, reverse: bool) -> (Vec<Vec<u8>>, Vec<RequestInfo>) {
+    /// Divides a region's accumulated sample traffic into `bucket_count` evenly-sized (by access
+    /// count) buckets instead of choosing a single split key -- advisory sub-region boundaries
+    /// for finer-grained scheduling, not a physical split, so unlike `split_keys` there's no
+    /// balance/contained-score rejection or minimum-gap guard: every quantile just gets the
+    /// nearest sample at or past it. See [`bucket_stats`].
+    fn collect_buckets(
+        key_ranges: &[Vec<KeyRange>],
+        config: &SplitConfig,
+        bucket_count: usize,
+    ) -> (Vec<Vec<u8>>, Vec<usize>) {
+        let samples = Recorder::collect_samples(key_ranges, config);
+        bucket_stats(samples, bucket_count)
+    }
+
+    fn choose_bounds(mut req_infos: Vec<RequestInfo>, ratio_split_info: &RatioSplitInfo, reverse: bool) -> (Vec<Vec<u8>>, Vec<RequestInfo>) {
         if !reverse {
             req_infos.sort_by(|a, b| a.start_key.cmp(&b.start_key));
         } else {
             req_infos.sort_by(|a, b| b.end_key.cmp(&a.end_key));
         }
 
-        let mut sum: f64 = 0.0;
-        if ratio_split_info.dim_id == 0 {   // IO dimension: bytes rate
-            for req_info in req_infos.iter() {
-                sum += req_info.bytes as f64;
-            }
-        } else {    // CPU dimension: qps
-            sum = req_infos.len() as f64;
-        }
-        
+        let loads = combined_loads(&req_infos, &ratio_split_info.dims_or_default());
+        let sum: f64 = loads.iter().sum();
+
         let splitted_ratios = {
             let mut ratios = vec![];
             let mut ratio = ratio_split_info.ratio;
@@ -304,7 +951,7 @@ impl Recorder {
         let mut cur_load = 0.0;
         for i in 0..req_infos.len() {
             let req_info = &req_infos[i];
-            cur_load += req_info.get_load(ratio_split_info.dim_id);
+            cur_load += loads[i];
             while cur_target < target_loads.len() && cur_load >= target_loads[cur_target] {
                 let key = if !reverse {
                     &req_info.start_key
@@ -323,40 +970,31 @@ impl Recorder {
         (target_keys, req_infos)
     }
 
-    fn choose_middle(&self, req_infos: &Vec<RequestInfo>, left_bound: &Vec<u8>, right_bound: &Vec<u8>) -> Vec<u8> {
-        let mut target_key = left_bound;
-        
-        // the most proper split-key is in [left_bound, right_bound], we choose the middle key as the split-key
+    // Single streaming pass over `req_infos`: every contained request's `start_key` is pushed
+    // into a fresh `StreamingMedian` (`O(log n)` each) instead of the old two-pass count-then-find,
+    // which walked the contained range twice -- once to count it, once to find the key at the
+    // midpoint index. `ratio_split_req_infos` calls this once per bound pair; `choose_bounds`,
+    // which produces those pairs, is unrelated and still sorts `req_infos` up front.
+    fn choose_middle(req_infos: &Vec<RequestInfo>, left_bound: &Vec<u8>, right_bound: &Vec<u8>) -> Vec<u8> {
+        let mut median = StreamingMedian::new();
         let mut contained_num = 0;
         for req_info in req_infos {
             if req_info.start_key.cmp(&left_bound) == Ordering::Greater && req_info.end_key.cmp(&right_bound) == Ordering::Less {
                 contained_num += 1;
+                median.push(req_info.start_key.clone());
             }
             if req_info.start_key.cmp(&right_bound) == Ordering::Greater {
                 break;
             }
         }
-        
-        let target = contained_num / 2;
-        let mut current = 0;
-        for req_info in req_infos {
-            if req_info.start_key.cmp(&left_bound) == Ordering::Greater && req_info.end_key.cmp(&right_bound) == Ordering::Less {
-                current += 1;
-                if current >= target {
-                    target_key = &req_info.start_key;
-                    break;
-                }
-            }
-            if req_info.start_key.cmp(&right_bound) == Ordering::Greater {
-                break;
-            }
-        }
+
+        let target_key = median.median().unwrap_or_else(|| left_bound.clone());
         info!("choose_middle in ratio based splitting"; "split_key" => format!("{:?}", hex::encode_upper(&target_key)), "contained candidate ranges" => contained_num);
 
-        target_key.clone()
+        target_key
     }
 
-    fn dedup_keys(&self, input: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    fn dedup_keys(input: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
         let mut output = vec![];
         if input.len() >= 1 {
             output.push(input[0].clone());
@@ -373,14 +1011,13 @@ impl Recorder {
         output
     }
 
-    fn ratio_split(&mut self, _config: &SplitConfig, ratio_split_info: &RatioSplitInfo) -> Vec<Vec<u8>> {
-        let mut req_infos = vec![];
-        for req_infos_part in &mut self.req_infos {
-            req_infos.append(req_infos_part);
-        }
-
-        let (right_bounds, req_infos) = self.choose_bounds(req_infos, ratio_split_info, true);
-        let (left_bounds, req_infos) = self.choose_bounds(req_infos, ratio_split_info, false);
+    /// Pure variant of the per-region ratio-split scan, taking the recorder's flattened
+    /// `req_infos` history by value instead of draining `self.req_infos` -- same split as
+    /// [`Recorder::collect_key_ranges`], so `AutoSplitController::process_ratio_split` can run
+    /// one of these per hot region concurrently via `par_iter`.
+    fn ratio_split_req_infos(req_infos: Vec<RequestInfo>, ratio_split_info: &RatioSplitInfo) -> Vec<Vec<u8>> {
+        let (right_bounds, req_infos) = Recorder::choose_bounds(req_infos, ratio_split_info, true);
+        let (left_bounds, req_infos) = Recorder::choose_bounds(req_infos, ratio_split_info, false);
 
         if left_bounds.len() == 0 || right_bounds.len() == 0 || left_bounds.len() != right_bounds.len() {
             warn!("choose_bounds does not work in ratio based splitting"; "left_bounds len" => left_bounds.len(), "right_bounds len" => right_bounds.len());
@@ -390,14 +1027,14 @@ impl Recorder {
         // use middle key of each range as the splitted key.
         let mut target_keys = vec![];
         for i in 0..left_bounds.len() {
-            target_keys.push(self.choose_middle(&req_infos, &left_bounds[i], &right_bounds[i]));
+            target_keys.push(Recorder::choose_middle(&req_infos, &left_bounds[i], &right_bounds[i]));
         }
 
         let before_len = target_keys.len();
-        let deduped_keys = self.dedup_keys(target_keys);
+        let deduped_keys = Recorder::dedup_keys(target_keys);
 
         info!("ratio split region"; "dim id" => ratio_split_info.dim_id, "ratio" => ratio_split_info.ratio, "before_dedup len" => before_len, "after_dedup len" => deduped_keys.len());
-        
+
         deduped_keys
     }
 
@@ -459,6 +1096,81 @@ impl Recorder {
         }
         return vec![];
     }
+
+    /// Produces up to `batch_split_limit` split keys from one scan over `samples`, instead of
+    /// the single best-balanced key `split_key` chooses -- for a region hot enough that one
+    /// split still leaves both halves hot. `batch_split_limit <= 1` falls back to `split_key`
+    /// unchanged, so existing single-split callers are unaffected.
+    ///
+    /// Quantile-based, built on the existing `prefix_sum` helper: samples are sorted ascending
+    /// by key, then `prefix_sum`'d by each sample's total access count (`left + right +
+    /// contained`). For each target fraction `i / batch_split_limit` (`i` in
+    /// `1..batch_split_limit`), the first sample whose cumulative count crosses that fraction of
+    /// the total is a candidate, subject to the same `split_balance_score`/`split_contained_score`
+    /// gates `split_key` uses, plus a minimum sample-index gap from the previously chosen
+    /// candidate so two nearby quantiles don't collapse onto adjacent (effectively duplicate)
+    /// keys.
+    fn split_keys(
+        mut samples: Vec<Sample>,
+        split_balance_score: f64,
+        split_contained_score: f64,
+        sample_threshold: i32,
+        batch_split_limit: usize,
+    ) -> Vec<Vec<u8>> {
+        if batch_split_limit <= 1 {
+            let key = Recorder::split_key(
+                samples,
+                split_balance_score,
+                split_contained_score,
+                sample_threshold,
+            );
+            return if key.is_empty() { vec![] } else { vec![key] };
+        }
+
+        samples.sort_by(|a, b| a.key.cmp(&b.key));
+        let prefix = prefix_sum(samples.iter(), |s| (s.left + s.right + s.contained) as usize);
+        let total = match prefix.last() {
+            Some(total) if *total > 0 => *total,
+            _ => return vec![],
+        };
+
+        // Quantiles landing within `min_gap` samples of the previous pick are skipped rather
+        // than accepted as a near-duplicate key.
+        let min_gap = (samples.len() / (batch_split_limit * 2)).max(1);
+
+        let mut chosen = vec![];
+        let mut last_index: Option<usize> = None;
+        for i in 1..batch_split_limit {
+            let target = (i as f64 / batch_split_limit as f64) * total as f64;
+            let idx = match prefix.iter().position(|&cum| cum as f64 >= target) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            if last_index.map_or(false, |last| idx <= last + min_gap) {
+                continue;
+            }
+
+            let sample = &samples[idx];
+            let sampled = sample.left + sample.right + sample.contained;
+            if (sample.left + sample.right) == 0 || sampled < sample_threshold {
+                continue;
+            }
+            let diff = (sample.left - sample.right) as f64;
+            let balance_score = diff.abs() / (sample.left + sample.right) as f64;
+            if balance_score >= split_balance_score {
+                continue;
+            }
+            let contained_score = sample.contained as f64 / sampled as f64;
+            if contained_score >= split_contained_score {
+                continue;
+            }
+
+            chosen.push(idx);
+            last_index = Some(idx);
+        }
+
+        chosen.into_iter().map(|idx| samples[idx].key.clone()).collect()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -467,6 +1179,11 @@ pub struct ReadStats {
     pub region_infos: HashMap<u64, RegionInfo>,
     pub sample_num: usize,
     pub rw_type: u64,
+    /// Same convention as [`RatioSplitInfo::dim_id`]: `0` (the default) selects the IO/bytes
+    /// dimension, biasing every [`RegionInfo::add_req_infos`] reservoir it feeds toward the
+    /// heaviest requests; anything else keeps the original count-weighted reservoir. Set the
+    /// field directly (e.g. `stats.dim_id = 1`) before recording to switch dimensions.
+    pub dim_id: u64,
 }
 
 impl ReadStats {
@@ -476,6 +1193,7 @@ impl ReadStats {
             region_infos: HashMap::default(),
             flows: HashMap::default(),
             rw_type: 0,
+            dim_id: 0,
         }
     }
 
@@ -485,9 +1203,24 @@ impl ReadStats {
             region_infos: HashMap::default(),
             flows: HashMap::default(),
             rw_type: 1,
+            dim_id: 0,
         }
     }
 
+    /// Registers this region's sub-region bucket boundaries so subsequent `add_qps`/
+    /// `add_qps_batch`/`add_flow_bytes`/`add_req_info` calls also accumulate a per-bucket
+    /// breakdown (see [`RegionInfo::bucket_loads`]) that a downstream balancer can split on
+    /// directly, instead of only ever seeing whole-region totals.
+    pub fn set_bucket_keys(&mut self, region_id: u64, peer: &Peer, bucket_keys: Vec<Vec<u8>>) {
+        let num = self.sample_num;
+        let region_info = self
+            .region_infos
+            .entry(region_id)
+            .or_insert_with(|| RegionInfo::new(num));
+        region_info.update_peer(peer);
+        region_info.set_bucket_keys(bucket_keys);
+    }
+
     pub fn add_qps(&mut self, region_id: u64, peer: &Peer, key_range: KeyRange) {
         self.add_qps_batch(region_id, peer, vec![key_range]);
     }
@@ -502,6 +1235,29 @@ impl ReadStats {
         region_info.add_key_ranges(key_ranges);
     }
 
+    /// Records `bytes` of read flow against `key_range` for `region_id`, independent of
+    /// `add_qps`'s request count -- see [`RegionInfo::flow_bytes`]. Named `add_flow_bytes` (not
+    /// `add_flow`) because that name's already taken by the `FlowStatistics` write/data
+    /// accumulator below, which tracks a different thing.
+    pub fn add_flow_bytes(&mut self, region_id: u64, peer: &Peer, key_range: KeyRange, bytes: usize) {
+        self.add_flow_bytes_batch(region_id, peer, vec![(key_range, bytes)]);
+    }
+
+    pub fn add_flow_bytes_batch(
+        &mut self,
+        region_id: u64,
+        peer: &Peer,
+        key_ranges: Vec<(KeyRange, usize)>,
+    ) {
+        let num = self.sample_num;
+        let region_info = self
+            .region_infos
+            .entry(region_id)
+            .or_insert_with(|| RegionInfo::new(num));
+        region_info.update_peer(peer);
+        region_info.add_flow_bytes(key_ranges);
+    }
+
     pub fn add_req_info(&mut self, region_id: u64, peer: &Peer, req_info: RequestInfo) {
         self.add_req_info_batch(region_id, peer, vec![req_info]);
     }
@@ -513,9 +1269,32 @@ impl ReadStats {
             .entry(region_id)
             .or_insert_with(|| RegionInfo::new(num));
         region_info.update_peer(peer);
+        region_info.update_dim(self.dim_id);
         region_info.add_req_infos(req_infos);
     }
 
+    pub fn add_hot_key(&mut self, region_id: u64, peer: &Peer, key: &[u8]) {
+        let num = self.sample_num;
+        let region_info = self
+            .region_infos
+            .entry(region_id)
+            .or_insert_with(|| RegionInfo::new(num));
+        region_info.update_peer(peer);
+        region_info.add_hot_key(key);
+    }
+
+    /// Records `secs` of CPU time spent serving `region_id`, for the CPU/load-based split path --
+    /// see [`RegionInfo::cpu_secs`].
+    pub fn add_cpu(&mut self, region_id: u64, peer: &Peer, secs: f64) {
+        let num = self.sample_num;
+        let region_info = self
+            .region_infos
+            .entry(region_id)
+            .or_insert_with(|| RegionInfo::new(num));
+        region_info.update_peer(peer);
+        region_info.add_cpu(secs);
+    }
+
     pub fn add_flow(&mut self, region_id: u64, write: &FlowStatistics, data: &FlowStatistics) {
         let flow_stats = self
             .flows
@@ -530,20 +1309,106 @@ impl ReadStats {
     }
 }
 
+/// How many of the most recent per-`flush`-round CPU samples [`CpuWindow`] averages over before
+/// comparing against `cpu_threshold` -- one bursty round of coprocessor work shouldn't be enough
+/// to flag a region as CPU-hot, only a sustained trend across several.
+const CPU_WINDOW_SIZE: usize = 10;
+
+/// Rolling average of a region's recent per-round CPU time, plus when it was last touched so
+/// `AutoSplitController::clear` can age stale entries out the same way it does `Recorder`s.
+#[derive(Debug, Clone)]
+struct CpuWindow {
+    samples: VecDeque<f64>,
+    last_update: SystemTime,
+}
+
+impl Default for CpuWindow {
+    fn default() -> Self {
+        CpuWindow {
+            samples: VecDeque::with_capacity(CPU_WINDOW_SIZE),
+            last_update: SystemTime::now(),
+        }
+    }
+}
+
+impl CpuWindow {
+    /// Folds in this round's `secs` and returns the window's new average.
+    fn push(&mut self, secs: f64) -> f64 {
+        self.samples.push_back(secs);
+        if self.samples.len() > CPU_WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.last_update = SystemTime::now();
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+}
+
 pub struct AutoSplitController {
     pub recorders: HashMap<u64, Recorder>,
+    /// Detection-window history for the byte-flow split path, mirroring `recorders` but keyed
+    /// off `byte_threshold` instead of `cfg.qps_threshold` -- see `flush`.
+    pub byte_recorders: HashMap<u64, Recorder>,
+    /// Detection-window history for the CPU/load-based split path, mirroring `byte_recorders` but
+    /// keyed off `cpu_threshold` against each region's smoothed [`CpuWindow`] average -- see
+    /// `flush`.
+    pub cpu_recorders: HashMap<u64, Recorder>,
+    /// Per-region rolling average of recent CPU time, smoothing `ReadStats::add_cpu` samples
+    /// before they're compared to `cpu_threshold` -- see `CpuWindow`.
+    cpu_windows: HashMap<u64, CpuWindow>,
     cfg: SplitConfig,
     cfg_tracker: Tracker<SplitConfig>,
     pub ratio_split_maps: Arc<Mutex<HashMap<u64, RatioSplitInfo>>>,
+    /// Load dimension `flush`'s top-N hot-region report is ordered by; set directly (e.g.
+    /// `controller.hot_region_sort_key = HotRegionSortKey::Bytes`) to switch it.
+    pub hot_region_sort_key: HotRegionSortKey,
+    /// Read-flow (bytes) threshold for `flush`'s second, independent detection path: a region
+    /// qualifies for load-base splitting once *either* its qps exceeds `cfg.qps_threshold` or
+    /// its accumulated `flow_bytes` over the window exceeds this. Defaults to `usize::MAX`
+    /// (path disabled) so existing qps-only callers are unaffected until they opt in.
+    ///
+    /// TODO: this belongs on `SplitConfig`/`SplitConfigManager` so it's hot-reloadable like
+    /// `qps_threshold`, but both types are defined in `split_config.rs`, which this checkout
+    /// doesn't have (only this file exists under `store::worker`) -- same gap noted on
+    /// `RatioSplitInfo::dims`.
+    pub byte_threshold: usize,
+    /// Max split keys `flush`'s load-base path emits per hot region in one round (see
+    /// [`Recorder::split_keys`]). Defaults to `1`, matching the original single-split behavior;
+    /// same `SplitConfig`-placement TODO as `byte_threshold` above applies here too.
+    pub batch_split_limit: usize,
+    /// Minimum guaranteed count (see [`HotRangeSketch::guaranteed_hot`]) for a key range to be
+    /// included in `flush`'s [`HotRegionInfo::hot_ranges`] report. Defaults to `0`, i.e. every
+    /// tracked range is reported; same `SplitConfig`-placement TODO as `byte_threshold` above
+    /// applies here too.
+    pub hot_range_support_threshold: usize,
+    /// Smoothed-CPU-time (seconds) threshold for `flush`'s third, independent detection path: a
+    /// region qualifies for load-base splitting once its [`CpuWindow`]-smoothed `add_cpu` average
+    /// exceeds this, decoupling "should we look for a split key" from request/byte volume
+    /// entirely. Defaults to `f64::MAX` (path disabled); same `SplitConfig`-placement TODO as
+    /// `byte_threshold` above applies here too.
+    pub cpu_threshold: f64,
+    /// How many equal-traffic buckets `flush` divides each hot region's accumulated sample
+    /// traffic into (see [`BucketStat`]), refreshed every round alongside `split_infos`.
+    /// `0`/`1` (the default) disables bucket reporting entirely; same `SplitConfig`-placement
+    /// TODO as `byte_threshold` above applies here too.
+    pub bucket_count: usize,
 }
 
 impl AutoSplitController {
     pub fn new(config_manager: SplitConfigManager) -> AutoSplitController {
         AutoSplitController {
             recorders: HashMap::default(),
+            byte_recorders: HashMap::default(),
+            cpu_recorders: HashMap::default(),
+            cpu_windows: HashMap::default(),
             cfg: config_manager.value().clone(),
             cfg_tracker: config_manager.0.clone().tracker("split_hub".to_owned()),
             ratio_split_maps: Arc::new(Mutex::new(HashMap::default())),
+            hot_region_sort_key: HotRegionSortKey::default(),
+            byte_threshold: usize::MAX,
+            batch_split_limit: 1,
+            hot_range_support_threshold: 0,
+            cpu_threshold: f64::MAX,
+            bucket_count: 0,
         }
     }
 
@@ -551,28 +1416,127 @@ impl AutoSplitController {
         AutoSplitController::new(SplitConfigManager::default())
     }
 
-    pub fn flush(&mut self, others: Vec<ReadStats>) -> (Vec<usize>, Vec<SplitInfo>) {
-        let mut split_infos = Vec::default();
-        let mut top = BinaryHeap::with_capacity(TOP_N as usize);
+    pub fn flush(
+        &mut self,
+        others: Vec<ReadStats>,
+    ) -> (Vec<HotRegionInfo>, Vec<SplitInfo>, Vec<BucketStat>) {
+        // Bounded top-`TOP_N` max-heap over whichever load dimension `hot_region_sort_key`
+        // selects, same reversed-`Ord`-for-eviction trick `weighted_sample`'s `Slot` uses: the
+        // `BinaryHeap` (a max-heap) pops the *smallest* key first, i.e. the weakest hot-region
+        // candidate, evicted once a heavier one arrives and the heap is already at `TOP_N`.
+        struct HotSlot {
+            key: usize,
+            info: HotRegionInfo,
+        }
+        impl PartialEq for HotSlot {
+            fn eq(&self, other: &Self) -> bool {
+                self.key == other.key
+            }
+        }
+        impl Eq for HotSlot {}
+        impl PartialOrd for HotSlot {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for HotSlot {
+            fn cmp(&self, other: &Self) -> Ordering {
+                other.key.cmp(&self.key)
+            }
+        }
+
+        let mut top: BinaryHeap<HotSlot> = BinaryHeap::with_capacity(TOP_N);
 
         // collect from different thread
-        let mut region_infos_map = HashMap::default(); // regionID-regionInfos
+        let mut region_infos_map = HashMap::default(); // regionID-(regionInfos, rw_type)
         let capacity = others.len();
         for other in others {
+            let rw_type = other.rw_type;
             for (region_id, region_info) in other.region_infos {
-                if region_info.key_ranges.len() >= self.cfg.sample_num {
+                // Either sample feeding this round's scan (qps path's `key_ranges` or the byte
+                // path's `flow_key_ranges`) reaching `sample_num` is enough to consider the
+                // region this round -- whichever path isn't full yet simply won't trigger below.
+                // A region can also be purely CPU-hot with neither sample full yet (e.g. a small
+                // range driving heavy coprocessor work), so `cpu_secs > 0` admits it too -- the
+                // CPU path below only acts once `smoothed_cpu` actually crosses `cpu_threshold`.
+                if region_info.key_ranges.len() >= self.cfg.sample_num
+                    || region_info.flow_key_ranges.len() >= self.cfg.sample_num
+                    || region_info.cpu_secs > 0.0
+                {
                     let region_infos = region_infos_map
                         .entry(region_id)
                         .or_insert_with(|| Vec::with_capacity(capacity));
-                    region_infos.push(region_info);
+                    region_infos.push((region_info, rw_type));
                 }
             }
         }
 
-        for (region_id, region_infos) in region_infos_map {
-            let pre_sum = prefix_sum(region_infos.iter(), RegionInfo::get_qps);
+        // Serial pass: fold each region's freshly sampled key ranges into its `Recorder` (cheap
+        // bookkeeping that owns `self.recorders`), collecting a snapshot of the history for any
+        // `Recorder` that's now ready to decide a split key. The CPU-heavy scan over that history
+        // happens below, off the main thread.
+        let mut ready_regions = Vec::new();
+        for (region_id, region_infos_with_rw) in region_infos_map {
+            let pre_sum = prefix_sum(region_infos_with_rw.iter(), |(ri, _)| ri.qps);
 
             let qps = *pre_sum.last().unwrap(); // region_infos is not empty
+            let bytes: usize = region_infos_with_rw.iter().map(|(ri, _)| ri.bytes).sum();
+            let keys: usize = region_infos_with_rw.iter().map(|(ri, _)| ri.keys).sum();
+            let peer = region_infos_with_rw[0].0.peer.clone();
+            let rw_type = region_infos_with_rw[0].1;
+            let hot_ranges = HotRangeSketch::merge(region_infos_with_rw.iter().map(|(ri, _)| &ri.hot_ranges))
+                .guaranteed_hot(self.hot_range_support_threshold);
+
+            let sort_value = match self.hot_region_sort_key {
+                HotRegionSortKey::Qps => qps,
+                HotRegionSortKey::Bytes => bytes,
+            };
+            let hot_info = HotRegionInfo {
+                region_id,
+                peer: peer.clone(),
+                qps,
+                bytes,
+                keys,
+                rw_type,
+                hot_ranges,
+            };
+            if top.len() < TOP_N {
+                top.push(HotSlot {
+                    key: sort_value,
+                    info: hot_info,
+                });
+            } else if top.peek().map_or(false, |weakest| sort_value > weakest.key) {
+                top.pop();
+                top.push(HotSlot {
+                    key: sort_value,
+                    info: hot_info,
+                });
+            }
+
+            // Snapshot the byte-flow and CPU paths' inputs (`flow_bytes`, `flow_key_ranges`,
+            // `cpu_secs`) before `region_infos_with_rw` is consumed below.
+            let flow_bytes: usize = region_infos_with_rw.iter().map(|(ri, _)| ri.flow_bytes).sum();
+            let flattened_flow: Vec<(KeyRange, usize)> = region_infos_with_rw
+                .iter()
+                .flat_map(|(ri, _)| ri.flow_key_ranges.clone())
+                .collect();
+            let cpu_secs: f64 = region_infos_with_rw.iter().map(|(ri, _)| ri.cpu_secs).sum();
+
+            let region_infos: Vec<RegionInfo> =
+                region_infos_with_rw.into_iter().map(|(ri, _)| ri).collect();
+            // The CPU path needs its own pool of key ranges to sample from, taken before the qps
+            // path below consumes `region_infos`. `pre_sum` is qps-weighted and can't be reused
+            // here: a region driven purely by `add_cpu`/`add_flow_bytes_batch` (the case this path
+            // exists for) has `qps == 0`, which would make every weight in `pre_sum` zero too.
+            // Pooling both `key_ranges` (from `add_qps`) and `flattened_flow` (from
+            // `add_flow_bytes_batch`) means the CPU path still has something to sample even when
+            // only one of those two is populated.
+            let cpu_key_ranges_pool: Vec<KeyRange> = region_infos
+                .iter()
+                .flat_map(|ri| ri.key_ranges.clone())
+                .chain(flattened_flow.iter().map(|(key_range, _)| key_range.clone()))
+                .collect();
+
             let num = self.cfg.detect_times;
             if qps > self.cfg.qps_threshold {
                 let recorder = self
@@ -580,7 +1544,7 @@ impl AutoSplitController {
                     .entry(region_id)
                     .or_insert_with(|| Recorder::new(num));
 
-                recorder.update_peer(&region_infos[0].peer);
+                recorder.update_peer(&peer);
 
                 let key_ranges = sample(
                     self.cfg.sample_num,
@@ -591,29 +1555,143 @@ impl AutoSplitController {
 
                 recorder.record(key_ranges);
                 if recorder.is_ready() {
-                    let key = recorder.collect(&self.cfg);
-                    if !key.is_empty() {
-                        let split_info = SplitInfo {
-                            region_id,
-                            split_keys: vec![Key::from_raw(&key).into_encoded()],
-                            peer: recorder.peer.clone(),
-                        };
-                        split_infos.push(split_info);
-                        info!("load base split region";"region_id"=>region_id);
-                    }
-                    self.recorders.remove(&region_id);
+                    ready_regions.push((region_id, recorder.peer.clone(), recorder.key_ranges.clone()));
                 }
             } else {
                 self.recorders.remove_entry(&region_id);
             }
-            top.push(qps);
+
+            // Byte-flow path: independent of the qps path above, so a region that's byte-hot on
+            // a handful of large scans (never crossing `qps_threshold`) still gets detected.
+            // Reuses the same `Recorder`/`collect_key_ranges` machinery below, just fed a
+            // byte-weighted sample (`weighted_sample`, already applied once per-source inside
+            // `RegionInfo::add_flow_bytes`; applying it again here merges this round's
+            // multiple sources the same way `sample` merges `key_ranges` for the qps path).
+            if flow_bytes > self.byte_threshold {
+                let byte_recorder = self
+                    .byte_recorders
+                    .entry(region_id)
+                    .or_insert_with(|| Recorder::new(num));
+
+                byte_recorder.update_peer(&peer);
+
+                let byte_key_ranges: Vec<KeyRange> =
+                    weighted_sample(self.cfg.sample_num, flattened_flow, |(_, bytes)| {
+                        *bytes as f64
+                    })
+                    .into_iter()
+                    .map(|(key_range, _)| key_range)
+                    .collect();
+
+                byte_recorder.record(byte_key_ranges);
+                if byte_recorder.is_ready() {
+                    ready_regions.push((
+                        region_id,
+                        byte_recorder.peer.clone(),
+                        byte_recorder.key_ranges.clone(),
+                    ));
+                    self.byte_recorders.remove(&region_id);
+                }
+            } else {
+                self.byte_recorders.remove_entry(&region_id);
+            }
+
+            // CPU/load-based path: independent of both paths above, so a region running heavy
+            // coprocessor work over a small range gets detected even if its request count and
+            // byte volume never cross their own thresholds. `cpu_secs` is smoothed through a
+            // `CpuWindow` first -- a single bursty round isn't enough, only a sustained trend --
+            // and, once flagged, samples `cpu_key_ranges_pool` with an equal weight per key range
+            // (there's no finer-grained per-range CPU cost to weight by), the same reservoir
+            // sampling the byte-flow path uses rather than reusing the qps path's `sample`.
+            let smoothed_cpu = self
+                .cpu_windows
+                .entry(region_id)
+                .or_insert_with(CpuWindow::default)
+                .push(cpu_secs);
+            if smoothed_cpu > self.cpu_threshold {
+                let cpu_recorder = self
+                    .cpu_recorders
+                    .entry(region_id)
+                    .or_insert_with(|| Recorder::new(num));
+
+                cpu_recorder.update_peer(&peer);
+
+                let cpu_key_ranges =
+                    weighted_sample(self.cfg.sample_num, cpu_key_ranges_pool, |_| 1.0);
+
+                cpu_recorder.record(cpu_key_ranges);
+                if cpu_recorder.is_ready() {
+                    ready_regions.push((
+                        region_id,
+                        cpu_recorder.peer.clone(),
+                        cpu_recorder.key_ranges.clone(),
+                    ));
+                    self.cpu_recorders.remove(&region_id);
+                }
+            } else {
+                self.cpu_recorders.remove_entry(&region_id);
+            }
+        }
+
+        let mut top_regions: Vec<HotRegionInfo> = top.into_iter().map(|slot| slot.info).collect();
+        top_regions.sort_by(|a, b| match self.hot_region_sort_key {
+            HotRegionSortKey::Qps => b.qps.cmp(&a.qps),
+            HotRegionSortKey::Bytes => b.bytes.cmp(&a.bytes),
+        });
+
+        let config = self.cfg.clone();
+        let batch_split_limit = self.batch_split_limit;
+        let split_infos: Vec<SplitInfo> = ready_regions
+            .par_iter()
+            .filter_map(|(region_id, peer, key_ranges)| {
+                let keys = Recorder::collect_key_ranges(key_ranges, &config, batch_split_limit);
+                if keys.is_empty() {
+                    return None;
+                }
+                info!("load base split region";"region_id"=>region_id);
+                Some(SplitInfo {
+                    region_id: *region_id,
+                    split_keys: keys
+                        .iter()
+                        .map(|key| Key::from_raw(key).into_encoded())
+                        .collect(),
+                    peer: peer.clone(),
+                })
+            })
+            .collect();
+
+        // Merge back on the main thread: every region that was ready is done with this round's
+        // `Recorder` regardless of whether a split key was found (the byte-flow and CPU paths
+        // already dropped their ready `Recorder`s above, when they were snapshotted).
+        for (region_id, ..) in &ready_regions {
+            self.recorders.remove(region_id);
         }
 
-        (top.into_vec(), split_infos)
+        // Bucket reporting reuses the exact same `ready_regions` snapshot split detection just
+        // scanned, so a region gets both a split decision and refreshed bucket boundaries from
+        // one round's sample traffic.
+        let bucket_count = self.bucket_count;
+        let bucket_stats: Vec<BucketStat> = if bucket_count > 1 {
+            ready_regions
+                .par_iter()
+                .map(|(region_id, _, key_ranges)| {
+                    let (boundary_keys, traffic_per_bucket) =
+                        Recorder::collect_buckets(key_ranges, &config, bucket_count);
+                    BucketStat {
+                        region_id: *region_id,
+                        boundary_keys,
+                        traffic_per_bucket,
+                    }
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        (top_regions, split_infos, bucket_stats)
     }
 
     pub fn process_ratio_split(&mut self, others: Vec<ReadStats>) -> Vec<SplitInfo> {
-        let mut split_infos = Vec::default();
         let mut split_maps = self.ratio_split_maps.lock().unwrap();
 
         // collect from different thread
@@ -633,10 +1711,15 @@ impl AutoSplitController {
             }
         }
 
+        // Serial pass: same split as `flush` above -- fold each region's freshly sampled
+        // `RequestInfo`s into its `Recorder`, and for any `Recorder` that's ready, snapshot its
+        // flattened history plus the `RatioSplitInfo` it'll be scanned against into an owned
+        // work item so the scan itself can run off the main thread.
+        let mut ready_regions = Vec::new();
         for (region_id, region_infos) in region_infos_map {
             let num = self.cfg.detect_times;
             if split_maps.contains_key(&region_id) {
-                let ratio_split_info = split_maps.entry(region_id).or_insert_with(|| RatioSplitInfo::new());
+                let ratio_split_info = split_maps.entry(region_id).or_insert_with(|| RatioSplitInfo::new()).clone();
 
                 let recorder = self
                     .recorders
@@ -653,30 +1736,49 @@ impl AutoSplitController {
                 recorder.record_req_infos(req_infos);
 
                 if recorder.is_ready() {
-                    let split_keys = recorder.ratio_split(&self.cfg, ratio_split_info);
-                    if !split_keys.is_empty() {
-                        // let split_keys: Vec<Vec<u8>> = keys.iter().map(|key| Key::from_raw(&key).into_encoded()).collect();
-                        for split_key in &split_keys {
-                            info!("ratio split region";"region_id"=>region_id, "split_key"=>format!("{:?}", hex::encode_upper(&split_key)));
-                        }
-                        let split_info = SplitInfo {
-                            region_id,
-                            split_keys,
-                            peer: recorder.peer.clone(),
-                        };
-                        split_infos.push(split_info);
-                        split_maps.remove(&region_id);
-                        info!("ratio split region: success";"region_id"=>region_id);
-                    } else {
-                        info!("ratio split region: failed";"region_id"=>region_id);
-                    }
-                    self.recorders.remove(&region_id);
+                    let flattened: Vec<RequestInfo> =
+                        recorder.req_infos.iter().flatten().cloned().collect();
+                    ready_regions.push((region_id, recorder.peer.clone(), flattened, ratio_split_info));
                 }
             } else {
                 self.recorders.remove_entry(&region_id);
             }
         }
 
+        let results: Vec<(u64, Option<SplitInfo>)> = ready_regions
+            .par_iter()
+            .map(|(region_id, peer, req_infos, ratio_split_info)| {
+                let split_keys = Recorder::ratio_split_req_infos(req_infos.clone(), ratio_split_info);
+                if split_keys.is_empty() {
+                    info!("ratio split region: failed";"region_id"=>region_id);
+                    return (*region_id, None);
+                }
+                for split_key in &split_keys {
+                    info!("ratio split region";"region_id"=>region_id, "split_key"=>format!("{:?}", hex::encode_upper(&split_key)));
+                }
+                info!("ratio split region: success";"region_id"=>region_id);
+                (
+                    *region_id,
+                    Some(SplitInfo {
+                        region_id: *region_id,
+                        split_keys,
+                        peer: peer.clone(),
+                    }),
+                )
+            })
+            .collect();
+
+        // Merge back on the main thread: every region that was ready is done with this round's
+        // `Recorder`, and only the ones that actually produced split keys leave `split_maps`.
+        let mut split_infos = Vec::default();
+        for (region_id, result) in results {
+            self.recorders.remove(&region_id);
+            if let Some(split_info) = result {
+                split_maps.remove(&region_id);
+                split_infos.push(split_info);
+            }
+        }
+
         split_infos
     }
 
@@ -684,6 +1786,12 @@ impl AutoSplitController {
         let interval = Duration::from_secs(self.cfg.detect_times * 2);
         self.recorders
             .retain(|_, recorder| recorder.create_time.elapsed().unwrap() < interval);
+        self.byte_recorders
+            .retain(|_, recorder| recorder.create_time.elapsed().unwrap() < interval);
+        self.cpu_recorders
+            .retain(|_, recorder| recorder.create_time.elapsed().unwrap() < interval);
+        self.cpu_windows
+            .retain(|_, window| window.last_update.elapsed().unwrap() < interval);
     }
 
     pub fn refresh_cfg(&mut self) {
@@ -743,6 +1851,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_combined_loads() {
+        // One request is all bytes, the other all count: on a pure IO dimension (0) the first
+        // request carries the whole load; on a pure CPU dimension (other) each carries half
+        // (qps load is always `1.0` per request, see `RequestInfo::get_load`).
+        let heavy = RequestInfo {
+            bytes: 1000,
+            ..Default::default()
+        };
+        let light = RequestInfo {
+            bytes: 0,
+            ..Default::default()
+        };
+        let req_infos = vec![heavy, light];
+
+        let io_only = combined_loads(&req_infos, &[(0, 1.0)]);
+        assert_eq!(io_only, vec![1.0, 0.0]);
+
+        let cpu_only = combined_loads(&req_infos, &[(1, 1.0)]);
+        assert_eq!(cpu_only, vec![0.5, 0.5]);
+
+        // An even split across both dimensions should land halfway between the two: the heavy
+        // request still leads (it owns the whole IO share) but by less than it would alone.
+        let combined = combined_loads(&req_infos, &[(0, 0.5), (1, 0.5)]);
+        assert_eq!(combined, vec![0.75, 0.25]);
+        // Summing the per-request combined load still reproduces the normalized total, same
+        // contract `choose_bounds` relies on for its `sum`/`target_loads` split targets.
+        assert!((combined.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_streaming_median() {
+        let mut median = StreamingMedian::new();
+        for key in [b"a".to_vec(), b"c".to_vec(), b"e".to_vec(), b"g".to_vec(), b"i".to_vec()] {
+            median.push(key);
+        }
+        // Five ascending keys: the lower half (max-heap) holds the bottom three, so its top --
+        // the maintained median -- is the third-smallest key.
+        assert_eq!(median.median(), Some(b"e".to_vec()));
+
+        // With an even count the two halves end up the same size (three and three here), and
+        // the median still comes from the lower half's top -- the lower of the two middle keys.
+        median.push(b"b".to_vec());
+        assert_eq!(median.median(), Some(b"c".to_vec()));
+    }
+
+    #[test]
+    fn test_weighted_sample() {
+        // A handful of very heavy items among a sea of zero-weight ones should survive the
+        // reservoir almost every time -- zero weight is floored to 1 rather than excluded, so
+        // it's merely outcompeted, never disqualified.
+        let mut items: Vec<(u64, f64)> = (0..200).map(|i| (i, 0.0)).collect();
+        items.push((1000, 1_000_000.0));
+        items.push((1001, 1_000_000.0));
+        let sampled = weighted_sample(2, items, |(_, weight)| *weight);
+        assert_eq!(sampled.len(), 2);
+        let ids: Vec<u64> = sampled.iter().map(|(id, _)| *id).collect();
+        assert!(ids.contains(&1000) && ids.contains(&1001));
+
+        // Calling it again with fresh input (simulating the next detection window) doesn't
+        // carry any state over from the call above.
+        let next_window = vec![(2000, 5.0), (2001, 5.0)];
+        let sampled = weighted_sample(2, next_window, |(_, weight)| *weight);
+        assert_eq!(sampled.len(), 2);
+    }
+
     #[test]
     fn test_sample() {
         let sc = SampleCase { key: vec![b'c'] };
@@ -769,6 +1943,39 @@ mod tests {
         sc.sample_key(b"", b"d", Position::Contained);
     }
 
+    fn balanced_samples(keys: &[&[u8]]) -> Vec<Sample> {
+        keys.iter()
+            .map(|key| Sample {
+                key: key.to_vec(),
+                left: 45,
+                right: 55,
+                contained: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_keys_single_fallback() {
+        // `batch_split_limit <= 1` should produce exactly what `split_key` alone would.
+        let samples = balanced_samples(&[b"a0", b"a1", b"a2", b"a3"]);
+        let expect = Recorder::split_key(samples.clone(), 0.5, 0.9, 0);
+        let got = Recorder::split_keys(samples, 0.5, 0.9, 0, 1);
+        assert_eq!(got, vec![expect]);
+    }
+
+    #[test]
+    fn test_split_keys_batch() {
+        // Nine equally-loaded samples (100 accesses each, 900 total): batch_split_limit 3 asks
+        // for keys at the 1/3 and 2/3 cumulative-access marks, which land on the 3rd and 6th
+        // sample (cumulative counts 300 and 600).
+        let keys: Vec<Vec<u8>> = (0..9).map(|i| format!("k{}", i).into_bytes()).collect();
+        let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let samples = balanced_samples(&key_refs);
+
+        let split_keys = Recorder::split_keys(samples, 0.5, 0.9, 0, 3);
+        assert_eq!(split_keys, vec![keys[2].clone(), keys[5].clone()]);
+    }
+
     #[test]
     fn test_hub() {
         let mut hub = AutoSplitController::new(SplitConfigManager::default());
@@ -781,7 +1988,7 @@ mod tests {
                 qps_stats.add_qps(1, &Peer::default(), build_key_range(b"a", b"b", false));
                 qps_stats.add_qps(1, &Peer::default(), build_key_range(b"b", b"c", false));
             }
-            let (_, split_infos) = hub.flush(vec![qps_stats]);
+            let (_, split_infos, _) = hub.flush(vec![qps_stats]);
             if (i + 1) % hub.cfg.detect_times == 0 {
                 assert_eq!(split_infos.len(), 1);
                 assert_eq!(
@@ -794,6 +2001,206 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_hub_byte_flow_split() {
+        // Same shape as `test_hub`, but the qps path is disabled (`qps_threshold = usize::MAX`)
+        // and the region is driven hot purely through `add_flow_bytes` -- a region that never
+        // crosses a qps threshold should still split once its byte flow does.
+        let mut hub = AutoSplitController::new(SplitConfigManager::default());
+        hub.cfg.qps_threshold = usize::MAX;
+        hub.byte_threshold = 1;
+        hub.cfg.sample_threshold = 0;
+
+        for i in 0..100 {
+            let mut qps_stats = ReadStats::default();
+            for _ in 0..100 {
+                qps_stats.add_flow_bytes(1, &Peer::default(), build_key_range(b"a", b"b", false), 100);
+                qps_stats.add_flow_bytes(1, &Peer::default(), build_key_range(b"b", b"c", false), 100);
+            }
+            let (_, split_infos, _) = hub.flush(vec![qps_stats]);
+            if (i + 1) % hub.cfg.detect_times == 0 {
+                assert_eq!(split_infos.len(), 1);
+                assert_eq!(
+                    Key::from_encoded(split_infos[0].split_keys[0].clone())
+                        .into_raw()
+                        .unwrap(),
+                    b"b"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hub_cpu_split() {
+        // Same shape as `test_hub`, but the qps path is disabled (`qps_threshold = usize::MAX`)
+        // and the region is driven hot purely through `add_cpu`'s smoothed load -- a region that
+        // never crosses a qps threshold should still split once its CPU usage does. `add_qps`
+        // still runs so the region has key ranges to sample from once flagged CPU-hot.
+        let mut hub = AutoSplitController::new(SplitConfigManager::default());
+        hub.cfg.qps_threshold = usize::MAX;
+        hub.cpu_threshold = 1.0;
+        hub.cfg.sample_threshold = 0;
+
+        for i in 0..100 {
+            let mut qps_stats = ReadStats::default();
+            for _ in 0..100 {
+                qps_stats.add_qps(1, &Peer::default(), build_key_range(b"a", b"b", false));
+                qps_stats.add_qps(1, &Peer::default(), build_key_range(b"b", b"c", false));
+            }
+            qps_stats.add_cpu(1, &Peer::default(), 10.0);
+            let (_, split_infos, _) = hub.flush(vec![qps_stats]);
+            if (i + 1) % hub.cfg.detect_times == 0 {
+                assert_eq!(split_infos.len(), 1);
+                assert_eq!(
+                    Key::from_encoded(split_infos[0].split_keys[0].clone())
+                        .into_raw()
+                        .unwrap(),
+                    b"b"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hub_cpu_split_without_qps() {
+        // Regression test for the CPU path panicking (`rng.gen_range(0, 0)`) when it reused the
+        // qps path's `pre_sum`: a region entering `region_infos_map` purely through
+        // `add_flow_bytes`/`add_cpu`, with zero `add_qps` calls, has `qps == 0` throughout, so
+        // that `pre_sum` would be all zeros. The byte path is disabled too (`byte_threshold =
+        // usize::MAX`), so the split below can only have come from the CPU path sampling its own
+        // pool of key ranges.
+        let mut hub = AutoSplitController::new(SplitConfigManager::default());
+        hub.cfg.qps_threshold = usize::MAX;
+        hub.byte_threshold = usize::MAX;
+        hub.cpu_threshold = 1.0;
+        hub.cfg.sample_threshold = 0;
+
+        for i in 0..100 {
+            let mut qps_stats = ReadStats::default();
+            for _ in 0..100 {
+                qps_stats.add_flow_bytes(1, &Peer::default(), build_key_range(b"a", b"b", false), 100);
+                qps_stats.add_flow_bytes(1, &Peer::default(), build_key_range(b"b", b"c", false), 100);
+            }
+            qps_stats.add_cpu(1, &Peer::default(), 10.0);
+            let (_, split_infos, _) = hub.flush(vec![qps_stats]);
+            if (i + 1) % hub.cfg.detect_times == 0 {
+                assert_eq!(split_infos.len(), 1);
+                assert_eq!(
+                    Key::from_encoded(split_infos[0].split_keys[0].clone())
+                        .into_raw()
+                        .unwrap(),
+                    b"b"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hub_hot_regions() {
+        let mut hub = AutoSplitController::new(SplitConfigManager::default());
+        hub.cfg.sample_num = 1;
+        // Keep every region below the split-detection threshold so this test only exercises the
+        // hot-region report, not `Recorder` bookkeeping.
+        hub.cfg.qps_threshold = usize::MAX;
+        hub.hot_region_sort_key = HotRegionSortKey::Bytes;
+
+        let region_count = TOP_N + 5;
+        let mut qps_stats = ReadStats::default();
+        for region_id in 0..region_count as u64 {
+            // `add_qps` is what makes a region eligible for `flush`'s report at all (it gates on
+            // `key_ranges.len()`); `add_req_info` is what the report's `bytes`/`keys` come from.
+            qps_stats.add_qps(region_id, &Peer::default(), build_key_range(b"a", b"b", false));
+            qps_stats.add_req_info(
+                region_id,
+                &Peer::default(),
+                RequestInfo {
+                    start_key: b"a".to_vec(),
+                    end_key: b"b".to_vec(),
+                    bytes: (region_id + 1) as usize * 100,
+                    keys: 1,
+                    query_kind: QueryKind::Get,
+                },
+            );
+        }
+
+        let (top_regions, _, _) = hub.flush(vec![qps_stats]);
+        assert_eq!(top_regions.len(), TOP_N);
+        for pair in top_regions.windows(2) {
+            assert!(pair[0].bytes >= pair[1].bytes);
+        }
+        // The heaviest-byte regions are the highest region_ids here, and those are exactly the
+        // ones that should have survived the bounded top-`TOP_N` heap.
+        assert_eq!(top_regions[0].region_id, region_count as u64 - 1);
+        assert_eq!(top_regions[0].rw_type, 0);
+    }
+
+    #[test]
+    fn test_hub_hot_ranges() {
+        let mut hub = AutoSplitController::new(SplitConfigManager::default());
+        hub.cfg.sample_num = 1;
+        hub.cfg.qps_threshold = usize::MAX;
+        hub.hot_range_support_threshold = 3;
+
+        let mut qps_stats = ReadStats::default();
+        // `[a, b)` is hit 5 times, `[c, d)` 3 times, `[e, f)` just once -- only the first two
+        // should clear `hot_range_support_threshold`.
+        for _ in 0..5 {
+            qps_stats.add_qps(1, &Peer::default(), build_key_range(b"a", b"b", false));
+        }
+        for _ in 0..3 {
+            qps_stats.add_qps(1, &Peer::default(), build_key_range(b"c", b"d", false));
+        }
+        qps_stats.add_qps(1, &Peer::default(), build_key_range(b"e", b"f", false));
+
+        let (top_regions, _, _) = hub.flush(vec![qps_stats]);
+        assert_eq!(top_regions.len(), 1);
+        let hot_ranges = &top_regions[0].hot_ranges;
+        assert_eq!(hot_ranges.len(), 2);
+        assert_eq!(hot_ranges[0].start_key, b"a".to_vec());
+        assert_eq!(hot_ranges[0].count, 5);
+        assert_eq!(hot_ranges[1].start_key, b"c".to_vec());
+        assert_eq!(hot_ranges[1].count, 3);
+    }
+
+    #[test]
+    fn test_hot_range_sketch_byte_weighted() {
+        let mut sketch = HotRangeSketch::default();
+        // A single heavy flow observation should outweigh several tiny ones.
+        sketch.observe(b"a", b"b", 1000);
+        for _ in 0..5 {
+            sketch.observe(b"c", b"d", 1);
+        }
+        let top_ranges = sketch.top_ranges();
+        assert_eq!(top_ranges[0].start_key, b"a".to_vec());
+        assert_eq!(top_ranges[0].count, 1000);
+        assert_eq!(top_ranges[1].start_key, b"c".to_vec());
+        assert_eq!(top_ranges[1].count, 5);
+    }
+
+    #[test]
+    fn test_read_stats_bucket_load() {
+        let mut qps_stats = ReadStats::default();
+        // Buckets: [-inf, c), [c, f), [f, +inf).
+        qps_stats.set_bucket_keys(1, &Peer::default(), vec![b"c".to_vec(), b"f".to_vec()]);
+
+        // Entirely inside the first bucket.
+        qps_stats.add_qps(1, &Peer::default(), build_key_range(b"a", b"b", false));
+        // Straddles the first two buckets.
+        qps_stats.add_qps(1, &Peer::default(), build_key_range(b"b", b"d", false));
+        // Empty bounds fall back to the region's own bounds, covering every bucket.
+        qps_stats.add_qps(1, &Peer::default(), build_key_range(b"", b"", false));
+
+        let region_info = &qps_stats.region_infos[&1];
+        assert_eq!(region_info.bucket_loads.len(), 3);
+        // bucket 0: whole [a, b) (1) + half of [b, d) (0.5, rounds to 1) + a third of the
+        // whole-region observation (0.33, rounds to 0) = 2
+        assert_eq!(region_info.bucket_loads[0].qps, 2);
+        // bucket 1: half of [b, d) (0.5, rounds to 1) + a third of whole-region (rounds to 0) = 1
+        assert_eq!(region_info.bucket_loads[1].qps, 1);
+        // bucket 2: only a third of the whole-region observation (rounds to 0) = 0
+        assert_eq!(region_info.bucket_loads[2].qps, 0);
+    }
+
     const REGION_NUM: u64 = 1000;
     const KEY_RANGE_NUM: u64 = 1000;
 
@@ -0,0 +1,224 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Content-defined chunking for large raw values.
+//!
+//! [`raw_put`](super::Storage::raw_put) values larger than [`CDC_VALUE_THRESHOLD`] are split
+//! into variable-length, content-defined chunks with a FastCDC-style rolling hash: scanning
+//! byte-by-byte, `hash = (hash << 1) + GEAR[byte]`, and declaring a cut once `hash & mask == 0`.
+//! `mask` starts strict (few cut points, so chunks grow toward [`NORMAL_CHUNK_SIZE`]) and loosens
+//! past it (more cut points, so a chunk can't run away toward [`MAX_CHUNK_SIZE`]). Because the
+//! cut points follow the content rather than a fixed offset, appending to or re-uploading a value
+//! only changes the chunks that actually differ. Each chunk is stored once, keyed by its content
+//! hash; the original key holds a small manifest listing the ordered chunk hashes, which
+//! `raw_get`/`forward_raw_scan`/`reverse_raw_scan` recognize and transparently reassemble.
+//!
+//! A real dedicated column family for chunk content would need an `engine_traits` change outside
+//! this module's reach, so chunks and manifests share the caller's `cf`, separated from ordinary
+//! raw keys only by the `#`-prefixed [`chunk_key`] namespace -- not a real CF boundary, so it has
+//! to be enforced by hand: [`Storage::raw_put`](super::Storage::raw_put) and the other raw-key
+//! write paths reject a caller-supplied key in this namespace (see [`is_reserved_key`]) instead of
+//! silently colliding with a chunk, and the raw scan paths skip any entry in it instead of
+//! returning internal chunk content as an ordinary row.
+//!
+//! The manifest itself, though, replaces the value under the caller's *own* key, so it can't be
+//! namespaced away the same way -- an ordinary raw value stored there must still be
+//! distinguishable from a manifest on sight. [`MANIFEST_MAGIC`] is therefore a full 8-byte marker
+//! rather than a single tag byte, and [`ChunkManifest::decode`] additionally verifies a checksum
+//! over the rest of the encoding before accepting it as a manifest: an ordinary value now has to
+//! match both an 8-byte sequence *and* a checksum of its own trailing bytes to be misread, which
+//! requires deliberate construction rather than 9 arbitrary bytes lining up by chance.
+
+use std::collections::HashSet;
+use std::convert::TryInto;
+
+/// A value this size or smaller is stored inline, exactly as before chunking existed.
+pub const CDC_VALUE_THRESHOLD: usize = MAX_CHUNK_SIZE;
+
+const MIN_CHUNK_SIZE: usize = 2 * 1024;
+const NORMAL_CHUNK_SIZE: usize = 8 * 1024;
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+// `MASK_SMALL` has more bits set than `MASK_LARGE`, so it's less likely to match by chance:
+// applied below `NORMAL_CHUNK_SIZE`, it keeps chunks growing rather than cutting too early.
+// `MASK_LARGE` is applied above `NORMAL_CHUNK_SIZE` to force a cut soon, capping growth toward
+// `MAX_CHUNK_SIZE`.
+const MASK_SMALL: u64 = (1 << 14) - 1;
+const MASK_LARGE: u64 = (1 << 11) - 1;
+
+/// 8-byte marker in front of every encoded [`ChunkManifest`], long enough that an ordinary raw
+/// value starting with these exact bytes by chance is negligible -- [`ChunkManifest::decode`]
+/// also checks a checksum of the rest of the encoding, so even that chance collision isn't
+/// enough to be misread as a manifest.
+const MANIFEST_MAGIC: [u8; 8] = *b"\xc7TiKVCDC";
+
+/// Length of the fixed manifest header: [`MANIFEST_MAGIC`] (8 bytes) + a checksum of the encoded
+/// body (8 bytes) + the body's own `total_len` field (8 bytes), before any chunk hashes.
+const MANIFEST_HEADER_LEN: usize = 24;
+
+lazy_static! {
+    /// Fixed 256-entry rolling-hash table, deterministically derived from a constant seed so
+    /// every store computes the same chunk boundaries for the same bytes.
+    static ref GEAR: [u64; 256] = {
+        let mut table = [0u64; 256];
+        let mut seed: u64 = 0x9e3779b97f4a7c15;
+        for slot in table.iter_mut() {
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            *slot = z ^ (z >> 31);
+        }
+        table
+    };
+}
+
+/// Splits `value` into content-defined chunks. Never returns an empty chunk, and the chunks
+/// concatenate back into exactly `value`.
+fn split_chunks(value: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < value.len() {
+        let remaining = value.len() - start;
+        if remaining <= MAX_CHUNK_SIZE {
+            chunks.push(&value[start..]);
+            break;
+        }
+
+        let mut hash: u64 = 0;
+        let mut len = MIN_CHUNK_SIZE;
+        let mut cut = MAX_CHUNK_SIZE;
+        while len < MAX_CHUNK_SIZE {
+            hash = (hash << 1).wrapping_add(GEAR[value[start + len] as usize]);
+            let mask = if len < NORMAL_CHUNK_SIZE {
+                MASK_SMALL
+            } else {
+                MASK_LARGE
+            };
+            len += 1;
+            if hash & mask == 0 {
+                cut = len;
+                break;
+            }
+        }
+        chunks.push(&value[start..start + cut]);
+        start += cut;
+    }
+    chunks
+}
+
+/// Content hash used to address a chunk. Two chunks with the same hash are treated as
+/// identical, as with any content-addressed store.
+fn content_hash(chunk: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = FNV_OFFSET;
+    for &b in chunk {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// The byte [`chunk_key`] reserves as its namespace tag. Not a real CF boundary (see the module
+/// doc), so every raw-key write path must reject a caller-supplied key starting with this byte,
+/// and every raw scan path must skip entries that do, or a chunk could collide with or leak
+/// through as an ordinary raw row.
+const CHUNK_KEY_PREFIX: u8 = b'#';
+
+/// The raw key a chunk with `hash` is stored under. The `#` tag keeps chunk keys out of the
+/// ordinary raw key namespace sharing the same `cf`.
+pub fn chunk_key(hash: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(9);
+    key.push(CHUNK_KEY_PREFIX);
+    key.extend_from_slice(&hash.to_be_bytes());
+    key
+}
+
+/// Whether `key` falls in the reserved chunk-storage namespace, i.e. starts with
+/// [`CHUNK_KEY_PREFIX`]. Raw-key write paths reject such a caller-supplied key; raw scan paths
+/// skip such an entry rather than returning it as an ordinary row.
+pub fn is_reserved_key(key: &[u8]) -> bool {
+    key.first() == Some(&CHUNK_KEY_PREFIX)
+}
+
+/// The manifest written under a chunked value's original key in place of its content.
+pub struct ChunkManifest {
+    pub chunk_hashes: Vec<u64>,
+    pub total_len: usize,
+}
+
+impl ChunkManifest {
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(8 + self.chunk_hashes.len() * 8);
+        body.extend_from_slice(&(self.total_len as u64).to_le_bytes());
+        for hash in &self.chunk_hashes {
+            body.extend_from_slice(&hash.to_le_bytes());
+        }
+        let checksum = content_hash(&body);
+
+        let mut buf = Vec::with_capacity(16 + body.len());
+        buf.extend_from_slice(&MANIFEST_MAGIC);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+        buf.extend_from_slice(&body);
+        buf
+    }
+
+    /// Returns `None` when `value` isn't a manifest this module wrote, i.e. it's an ordinary
+    /// inline raw value. Requires both the leading [`MANIFEST_MAGIC`] bytes and a matching
+    /// checksum of the body that follows, so an ordinary value can't be misread as a manifest by
+    /// accident.
+    pub fn decode(value: &[u8]) -> Option<ChunkManifest> {
+        if value.len() < MANIFEST_HEADER_LEN
+            || value[..8] != MANIFEST_MAGIC[..]
+            || (value.len() - MANIFEST_HEADER_LEN) % 8 != 0
+        {
+            return None;
+        }
+        let checksum = u64::from_le_bytes(value[8..16].try_into().unwrap());
+        let body = &value[16..];
+        if content_hash(body) != checksum {
+            return None;
+        }
+        let total_len = u64::from_le_bytes(body[0..8].try_into().unwrap()) as usize;
+        let chunk_hashes = body[8..]
+            .chunks_exact(8)
+            .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        Some(ChunkManifest {
+            chunk_hashes,
+            total_len,
+        })
+    }
+}
+
+/// The writes `raw_put` should make for a value larger than [`CDC_VALUE_THRESHOLD`]: one put per
+/// distinct chunk, plus the manifest that replaces the value under the caller's key.
+pub struct ChunkedPut {
+    pub chunk_writes: Vec<(Vec<u8>, Vec<u8>)>,
+    pub manifest: Vec<u8>,
+}
+
+/// Chunks `value`. Callers should only do this once `value.len() > CDC_VALUE_THRESHOLD`;
+/// smaller values are cheaper to keep inline.
+pub fn chunk_value(value: Vec<u8>) -> ChunkedPut {
+    let mut chunk_hashes = Vec::new();
+    let mut chunk_writes = Vec::new();
+    let mut seen = HashSet::new();
+    for chunk in split_chunks(&value) {
+        let hash = content_hash(chunk);
+        chunk_hashes.push(hash);
+        if seen.insert(hash) {
+            chunk_writes.push((chunk_key(hash), chunk.to_vec()));
+        }
+    }
+
+    let manifest = ChunkManifest {
+        chunk_hashes,
+        total_len: value.len(),
+    }
+    .encode();
+    ChunkedPut {
+        chunk_writes,
+        manifest,
+    }
+}
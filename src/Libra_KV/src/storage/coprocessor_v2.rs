@@ -0,0 +1,219 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A plugin surface over the raw KV API, so user code can push filtering/aggregation logic down
+//! to the storage node instead of round-tripping every key through `raw_scan`/`raw_batch_get`.
+//!
+//! [`RawStorage`] is the capability a plugin actually sees: an `async` trait bridging this
+//! crate's callback/channel-based `raw_*` methods (see `test_raw_*` in
+//! [`super::Storage`](super::Storage)) into plain futures, scoped to the key range the plugin
+//! was invoked with so it can't read or write outside the region it was handed.
+//! [`PluginRegistry`] is how a server looks a plugin up by name and dispatches a request to it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures03::channel::oneshot;
+use kvproto::kvrpcpb::{Context, KeyRange};
+use txn_types::KvPair;
+
+use crate::storage::kv::Engine;
+use crate::storage::lock_manager::LockManager;
+use crate::storage::{Error, ErrorInner, Result, Storage};
+
+/// The capability a [`CoprocessorPlugin`] is given: raw reads and writes against a single CF,
+/// scoped to the region the plugin was dispatched for.
+#[async_trait]
+pub trait RawStorage: Send + Sync {
+    async fn get(&self, cf: String, key: Vec<u8>) -> Result<Option<Vec<u8>>>;
+    async fn put(&self, cf: String, key: Vec<u8>, value: Vec<u8>) -> Result<()>;
+    async fn delete(&self, cf: String, key: Vec<u8>) -> Result<()>;
+    async fn scan(&self, cf: String, range: KeyRange) -> Result<Vec<KvPair>>;
+    async fn delete_range(&self, cf: String, range: KeyRange) -> Result<()>;
+}
+
+/// `true` for `key` somewhere inside `region`'s `[start_key, end_key)` (an empty `end_key`
+/// means "unbounded").
+fn key_in_region(region: &KeyRange, key: &[u8]) -> bool {
+    key >= region.get_start_key()
+        && (region.get_end_key().is_empty() || key < region.get_end_key())
+}
+
+/// [`RawStorage`] bridging a plugin's calls into [`Storage`]'s callback/channel-based `raw_*`
+/// API, the same `oneshot`-per-call pattern [`TxnClient`](super::txn_client::TxnClient) uses for
+/// `sched_txn_command`. Every call is checked against `region` first, so a plugin scoped to one
+/// region can't read or write a key outside it even if it tries to.
+pub struct StorageRawStorage<E: Engine, L: LockManager> {
+    storage: Storage<E, L>,
+    ctx: Context,
+    region: KeyRange,
+}
+
+impl<E: Engine, L: LockManager> StorageRawStorage<E, L> {
+    pub fn new(storage: Storage<E, L>, ctx: Context, region: KeyRange) -> Self {
+        StorageRawStorage {
+            storage,
+            ctx,
+            region,
+        }
+    }
+
+    fn check_in_region(&self, key: &[u8]) -> Result<()> {
+        if key_in_region(&self.region, key) {
+            Ok(())
+        } else {
+            Err(box_err!(
+                "key is outside the region this plugin was scoped to"
+            ))
+        }
+    }
+
+    fn check_range_in_region(&self, range: &KeyRange) -> Result<()> {
+        self.check_in_region(range.get_start_key())?;
+        let region_end = self.region.get_end_key();
+        let end = range.get_end_key();
+        if region_end.is_empty() || (!end.is_empty() && end <= region_end) {
+            Ok(())
+        } else {
+            Err(box_err!(
+                "key range extends outside the region this plugin was scoped to"
+            ))
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Engine, L: LockManager> RawStorage for StorageRawStorage<E, L> {
+    async fn get(&self, cf: String, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        self.check_in_region(&key)?;
+        self.storage.raw_get(self.ctx.clone(), cf, key).await
+    }
+
+    async fn put(&self, cf: String, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.check_in_region(&key)?;
+        let (tx, rx) = oneshot::channel();
+        self.storage.raw_put(
+            self.ctx.clone(),
+            cf,
+            key,
+            value,
+            None,
+            Box::new(move |res| {
+                let _ = tx.send(res);
+            }),
+        )?;
+        rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+    }
+
+    async fn delete(&self, cf: String, key: Vec<u8>) -> Result<()> {
+        self.check_in_region(&key)?;
+        let (tx, rx) = oneshot::channel();
+        self.storage.raw_delete(
+            self.ctx.clone(),
+            cf,
+            key,
+            Box::new(move |res| {
+                let _ = tx.send(res);
+            }),
+        )?;
+        rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+    }
+
+    async fn scan(&self, cf: String, range: KeyRange) -> Result<Vec<KvPair>> {
+        self.check_range_in_region(&range)?;
+        let end_key = if range.get_end_key().is_empty() {
+            None
+        } else {
+            Some(range.get_end_key().to_vec())
+        };
+        let results = self
+            .storage
+            .raw_scan(
+                self.ctx.clone(),
+                cf,
+                range.get_start_key().to_vec(),
+                end_key,
+                usize::MAX,
+                false,
+                false,
+                0,
+                vec![],
+                crate::storage::raw_filter::RawValueFilter::None,
+            )
+            .await?;
+        Ok(results.into_iter().filter_map(std::result::Result::ok).collect())
+    }
+
+    async fn delete_range(&self, cf: String, range: KeyRange) -> Result<()> {
+        self.check_range_in_region(&range)?;
+        let (tx, rx) = oneshot::channel();
+        self.storage.raw_delete_range(
+            self.ctx.clone(),
+            cf,
+            range.get_start_key().to_vec(),
+            range.get_end_key().to_vec(),
+            Box::new(move |res| {
+                let _ = tx.send(res);
+            }),
+        )?;
+        rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+    }
+}
+
+/// A dynamically-registered handler for coprocessor requests, given an opaque request buffer, a
+/// key range, and a [`RawStorage`] scoped to that range.
+#[async_trait]
+pub trait CoprocessorPlugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn on_raw_coprocessor_request(
+        &self,
+        ranges: Vec<KeyRange>,
+        request: Vec<u8>,
+        storage: &(dyn RawStorage + '_),
+    ) -> Result<Vec<u8>>;
+}
+
+/// Looks plugins up by name and dispatches a request to the one that matches.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: HashMap<String, Arc<dyn CoprocessorPlugin>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        PluginRegistry {
+            plugins: HashMap::new(),
+        }
+    }
+
+    /// Loads `plugin` under its own [`CoprocessorPlugin::name`], replacing any plugin
+    /// previously registered under the same name.
+    pub fn register(&mut self, plugin: Arc<dyn CoprocessorPlugin>) {
+        self.plugins.insert(plugin.name().to_owned(), plugin);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn CoprocessorPlugin>> {
+        self.plugins.get(name).cloned()
+    }
+
+    /// Dispatches `request` to the plugin registered as `plugin_name`, handing it a
+    /// [`RawStorage`] scoped to `region`.
+    pub async fn dispatch<E: Engine, L: LockManager>(
+        &self,
+        plugin_name: &str,
+        storage: Storage<E, L>,
+        ctx: Context,
+        region: KeyRange,
+        ranges: Vec<KeyRange>,
+        request: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let plugin = self
+            .get(plugin_name)
+            .ok_or_else(|| box_err!("no coprocessor plugin registered as {:?}", plugin_name))?;
+        let raw_storage = StorageRawStorage::new(storage, ctx, region);
+        plugin
+            .on_raw_coprocessor_request(ranges, request, &raw_storage)
+            .await
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Apply-time lock collection for fast, store-wide lock resolution/GC.
+//!
+//! [`physical_scan_lock`](super::Storage::physical_scan_lock) reads the LOCK column family
+//! directly, one region at a time, which is cheap but can still miss locks written between
+//! two regions' scans. [`AppliedLockCollector`] closes that gap: once started with a
+//! `max_ts`, it buffers every lock observed on the apply path so a caller can scan all
+//! regions and then merge in anything the collector saw in the meantime.
+
+use kvproto::kvrpcpb::LockInfo;
+use txn_types::{Lock, TimeStamp};
+
+use crate::storage::Result;
+
+/// Once the buffer holds this many locks, newly observed locks are dropped and `overflow` is
+/// set instead, so a runaway workload can't grow the collector without bound.
+const MAX_COLLECTED_LOCKS: usize = 1024 * 1024;
+
+struct CollectorState {
+    max_ts: TimeStamp,
+    locks: Vec<LockInfo>,
+    overflow: bool,
+}
+
+/// A process-wide, registerable observer of locks written through the apply path.
+///
+/// Only one collection can be in flight at a time; starting a new one discards whatever the
+/// previous one had collected.
+#[derive(Default)]
+pub struct AppliedLockCollector {
+    state: std::sync::Mutex<Option<CollectorState>>,
+}
+
+impl AppliedLockCollector {
+    pub fn new() -> Self {
+        AppliedLockCollector {
+            state: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Starts buffering locks with `ts <= max_ts`, discarding any previously collected ones.
+    pub fn start_collecting(&self, max_ts: TimeStamp) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        *state = Some(CollectorState {
+            max_ts,
+            locks: Vec::new(),
+            overflow: false,
+        });
+        Ok(())
+    }
+
+    /// Returns everything collected so far, and whether the buffer overflowed its capacity.
+    pub fn get_collected_locks(&self) -> Result<(Vec<LockInfo>, bool)> {
+        let state = self.state.lock().unwrap();
+        match state.as_ref() {
+            Some(s) => Ok((s.locks.clone(), s.overflow)),
+            None => Err(box_err!("lock collector is not started")),
+        }
+    }
+
+    /// Stops collecting and discards the buffer.
+    pub fn stop_collecting(&self) -> Result<()> {
+        *self.state.lock().unwrap() = None;
+        Ok(())
+    }
+
+    /// Called from the apply path whenever a key in the LOCK column family changes.
+    /// `lock` is `None` when the lock at `key` was removed (e.g. commit/rollback).
+    pub fn observe_apply(&self, raw_key: &[u8], lock: Option<&Lock>) {
+        let mut state = self.state.lock().unwrap();
+        let state = match state.as_mut() {
+            Some(s) => s,
+            None => return,
+        };
+        let lock = match lock {
+            Some(lock) if lock.ts <= state.max_ts => lock,
+            _ => return,
+        };
+        if state.locks.len() >= MAX_COLLECTED_LOCKS {
+            state.overflow = true;
+            return;
+        }
+        state.locks.push(lock.clone().into_lock_info(raw_key.to_vec()));
+    }
+}
+
+lazy_static! {
+    /// The single apply-time lock collector shared by every region's apply path and by
+    /// `Storage::start_collecting_locks`/`get_collected_locks`/`stop_collecting_locks`.
+    pub static ref APPLIED_LOCK_COLLECTOR: AppliedLockCollector = AppliedLockCollector::new();
+}
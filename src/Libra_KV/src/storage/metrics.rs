@@ -17,7 +17,7 @@ use raftstore::store::{ReadStats, RequestInfo};
 use tikv_util::collections::HashMap;
 
 struct StorageLocalMetrics {
-    local_scan_details: HashMap<CommandKind, Statistics>,
+    local_scan_details: HashMap<(CommandKind, CommandPriority), Statistics>,
     local_read_stats: ReadStats,
     local_write_stats: ReadStats,
     local_last_update_write_time: Instant,
@@ -38,13 +38,14 @@ pub fn tls_flush<R: FlowStatsReporter>(reporter: &R) {
     TLS_STORAGE_METRICS.with(|m| {
         let mut m = m.borrow_mut();
 
-        for (cmd, stat) in m.local_scan_details.drain() {
+        for ((cmd, priority), stat) in m.local_scan_details.drain() {
             for (cf, cf_details) in stat.details_enum().iter() {
                 for (tag, count) in cf_details.iter() {
                     KV_COMMAND_SCAN_DETAILS_STATIC
                         .get(cmd)
                         .get((*cf).into())
                         .get((*tag).into())
+                        .get(priority)
                         .inc_by(*count as i64);
                 }
             }
@@ -59,23 +60,78 @@ pub fn tls_flush<R: FlowStatsReporter>(reporter: &R) {
     });
 }
 
+/// Reports one already-drained batch of write stats (handed off across the "write-info-push"
+/// channel by [`tls_maybe_flush_write`], since `write_stats`' owning thread is not this one) --
+/// mirrors [`tls_flush`]'s is-empty guard so an idle interval doesn't produce an empty report.
 pub fn tls_flush_write<R: FlowStatsReporter>(reporter: &Option<R>, write_stats: ReadStats) {
-    TLS_STORAGE_METRICS.with(|_| {
-        match reporter {
-            Some(rep) => {
-                rep.report_write_stats(write_stats);
-            }
-            None => {}
-        };
-        
+    if write_stats.is_empty() {
+        return;
+    }
+    if let Some(rep) = reporter {
+        rep.report_write_stats(write_stats);
+    }
+}
+
+/// How long a thread accumulates writes into `local_write_stats` before handing the batch off to
+/// the "write-info-push" thread, mirroring `HOT_KEY_SAMPLE_RATE`'s note: would move to `Config`
+/// once this tree has a `storage::config` module to put it in.
+pub const WRITE_STATS_FLUSH_INTERVAL_MS: u128 = 1000;
+
+/// Swaps out `local_write_stats` and sends it down `sender` once `WRITE_STATS_FLUSH_INTERVAL_MS`
+/// has elapsed since the last send -- shared by every `tls_collect_write_*` helper so flow/qps/
+/// req-info collection all batch on the same cadence instead of each tracking its own timer.
+fn tls_maybe_flush_write(m: &mut StorageLocalMetrics, sender: &Option<Sender<ReadStats>>) {
+    if (Instant::now() - m.local_last_update_write_time).as_millis() <= WRITE_STATS_FLUSH_INTERVAL_MS
+    {
+        return;
+    }
+    let mut write_stats = ReadStats::default_write();
+    mem::swap(&mut write_stats, &mut m.local_write_stats);
+    if let Some(s) = sender {
+        if s.send(write_stats).is_err() {
+            warn!("send write_stats failed, are we shutting down?")
+        }
+    }
+    m.local_last_update_write_time = Instant::now();
+}
+
+pub fn tls_collect_write_flow(
+    sender: &Option<Sender<ReadStats>>,
+    region_id: u64,
+    statistics: &Statistics,
+) {
+    TLS_STORAGE_METRICS.with(|m| {
+        let mut m = m.borrow_mut();
+        m.local_write_stats.add_flow(
+            region_id,
+            &statistics.write.flow_stats,
+            &statistics.data.flow_stats,
+        );
+        tls_maybe_flush_write(&mut m, sender);
     });
 }
 
-pub fn tls_collect_scan_details(cmd: CommandKind, stats: &Statistics) {
+pub fn tls_collect_write_qps(
+    sender: &Option<Sender<ReadStats>>,
+    region_id: u64,
+    peer: &metapb::Peer,
+    start_key: &[u8],
+    end_key: &[u8],
+    reverse_scan: bool,
+) {
+    TLS_STORAGE_METRICS.with(|m| {
+        let mut m = m.borrow_mut();
+        let key_range = build_key_range(start_key, end_key, reverse_scan);
+        m.local_write_stats.add_qps(region_id, peer, key_range);
+        tls_maybe_flush_write(&mut m, sender);
+    });
+}
+
+pub fn tls_collect_scan_details(cmd: CommandKind, priority: CommandPriority, stats: &Statistics) {
     TLS_STORAGE_METRICS.with(|m| {
         m.borrow_mut()
             .local_scan_details
-            .entry(cmd)
+            .entry((cmd, priority))
             .or_insert_with(Default::default)
             .add(stats);
     });
@@ -114,6 +170,31 @@ pub fn tls_collect_qps_batch(region_id: u64, peer: &metapb::Peer, key_ranges: Ve
     });
 }
 
+/// Every `HOT_KEY_SAMPLE_RATE`-th call folds `key` into this thread's region-local hot-key
+/// sketch (`RegionInfo::hot_keys`), bounding the per-key tracking overhead scans pay to report
+/// hot keys to PD alongside the QPS `tls_collect_qps` already reports. Would move to `Config`
+/// once this tree has a `storage::config` module to put it in (see `ReadPoolTuner`'s equivalent
+/// note).
+pub const HOT_KEY_SAMPLE_RATE: usize = 16;
+
+thread_local! {
+    static TLS_HOT_KEY_SAMPLE_COUNTER: RefCell<usize> = RefCell::new(0);
+}
+
+pub fn tls_collect_hot_key(region_id: u64, peer: &metapb::Peer, key: &[u8]) {
+    let sampled = TLS_HOT_KEY_SAMPLE_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        *c += 1;
+        *c % HOT_KEY_SAMPLE_RATE == 0
+    });
+    if !sampled {
+        return;
+    }
+    TLS_STORAGE_METRICS.with(|m| {
+        m.borrow_mut().local_read_stats.add_hot_key(region_id, peer, key);
+    });
+}
+
 pub fn tls_collect_req_info(
     region_id: u64,
     peer: &metapb::Peer,
@@ -163,17 +244,7 @@ pub fn tls_collect_write_req_info(
         req_info.bytes = write_size;
         req_info.keys = 1;
         m.local_write_stats.add_req_info(region_id, peer, req_info);
-
-        if (Instant::now() - m.local_last_update_write_time).as_millis() > 1000 {
-            let mut write_stats = ReadStats::default_write();
-            mem::swap(&mut write_stats, &mut m.local_write_stats);
-            if let Some(s) = sender {
-                if s.send(write_stats).is_err() {
-                    warn!("send write_stats failed, are we shutting down?")
-                }
-            }
-            m.local_last_update_write_time = Instant::now();
-        }
+        tls_maybe_flush_write(&mut m, sender);
     });
 }
 
@@ -194,6 +265,7 @@ make_auto_flush_static_metric! {
         check_txn_status,
         check_secondary_locks,
         scan_lock,
+        physical_scan_lock,
         resolve_lock,
         resolve_lock_lite,
         delete_range,
@@ -201,7 +273,9 @@ make_auto_flush_static_metric! {
         key_mvcc,
         start_ts_mvcc,
         raw_get,
+        raw_get_field,
         raw_batch_get,
+        raw_batch_get_encoded,
         raw_scan,
         raw_batch_scan,
         raw_put,
@@ -209,6 +283,9 @@ make_auto_flush_static_metric! {
         raw_delete,
         raw_delete_range,
         raw_batch_delete,
+        raw_compare_and_swap,
+        raw_batch_atomic,
+        raw_get_key_ttl,
     }
 
     pub label_enum CommandStageKind {
@@ -260,6 +337,7 @@ make_auto_flush_static_metric! {
         "req" => CommandKind,
         "cf" => GcKeysCF,
         "tag" => GcKeysDetail,
+        "priority" => CommandPriority,
     }
 
     pub struct SchedDurationVec: LocalHistogram {
@@ -272,6 +350,7 @@ make_auto_flush_static_metric! {
 
     pub struct KReadVec: LocalHistogram {
         "type" => CommandKind,
+        "priority" => CommandPriority,
     }
 
     pub struct KvCommandCounterVec: LocalIntCounter {
@@ -298,6 +377,18 @@ make_auto_flush_static_metric! {
     pub struct SchedCommandPriCounterVec: LocalIntCounter {
         "priority" => CommandPriority,
     }
+
+    pub struct SchedAsyncSnapshotDurationVec: LocalHistogram {
+        "type" => CommandKind,
+    }
+
+    pub struct SchedWaitForThreadDurationVec: LocalHistogram {
+        "type" => CommandKind,
+    }
+
+    pub struct SchedProcessBeforeWriteDurationVec: LocalHistogram {
+        "type" => CommandKind,
+    }
 }
 
 impl Into<GcKeysCF> for ServerGcKeysCF {
@@ -410,7 +501,7 @@ lazy_static! {
     pub static ref KV_COMMAND_KEYREAD_HISTOGRAM_VEC: HistogramVec = register_histogram_vec!(
         "tikv_scheduler_kv_command_key_read",
         "Bucketed histogram of keys read of a kv command",
-        &["type"],
+        &["type", "priority"],
         exponential_buckets(1.0, 2.0, 21).unwrap()
     )
     .unwrap();
@@ -419,7 +510,7 @@ lazy_static! {
     pub static ref KV_COMMAND_SCAN_DETAILS: IntCounterVec = register_int_counter_vec!(
         "tikv_scheduler_kv_scan_details",
         "Bucketed counter of kv keys scan details for each cf",
-        &["req", "cf", "tag"]
+        &["req", "cf", "tag", "priority"]
     )
     .unwrap();
     pub static ref KV_COMMAND_SCAN_DETAILS_STATIC: CommandScanDetails =
@@ -438,4 +529,64 @@ lazy_static! {
         "Counter of request exceed bound"
     )
     .unwrap();
+    pub static ref SCHED_ASYNC_SNAPSHOT_DURATION_VEC: HistogramVec = register_histogram_vec!(
+        "tikv_scheduler_async_snapshot_duration_seconds",
+        "Bucketed histogram of time spent waiting on an async snapshot, split from total command \
+         duration so queueing for the engine can be told apart from processing it",
+        &["type"],
+        exponential_buckets(0.0005, 2.0, 20).unwrap()
+    )
+    .unwrap();
+    pub static ref SCHED_ASYNC_SNAPSHOT_DURATION_STATIC: SchedAsyncSnapshotDurationVec =
+        auto_flush_from!(SCHED_ASYNC_SNAPSHOT_DURATION_VEC, SchedAsyncSnapshotDurationVec);
+    pub static ref SCHED_WAIT_FOR_THREAD_DURATION_VEC: HistogramVec = register_histogram_vec!(
+        "tikv_scheduler_wait_for_thread_duration_seconds",
+        "Bucketed histogram of time a command spends queued before a scheduler worker thread \
+         picks it up",
+        &["type"],
+        exponential_buckets(0.0005, 2.0, 20).unwrap()
+    )
+    .unwrap();
+    pub static ref SCHED_WAIT_FOR_THREAD_DURATION_STATIC: SchedWaitForThreadDurationVec =
+        auto_flush_from!(SCHED_WAIT_FOR_THREAD_DURATION_VEC, SchedWaitForThreadDurationVec);
+    pub static ref SCHED_PROCESS_BEFORE_WRITE_DURATION_VEC: HistogramVec = register_histogram_vec!(
+        "tikv_scheduler_process_before_write_duration_seconds",
+        "Bucketed histogram of time spent processing a command up to the point its write is \
+         handed to the engine, split out from the total processing-write duration",
+        &["type"],
+        exponential_buckets(0.0005, 2.0, 20).unwrap()
+    )
+    .unwrap();
+    pub static ref SCHED_PROCESS_BEFORE_WRITE_DURATION_STATIC: SchedProcessBeforeWriteDurationVec =
+        auto_flush_from!(
+            SCHED_PROCESS_BEFORE_WRITE_DURATION_VEC,
+            SchedProcessBeforeWriteDurationVec
+        );
+}
+
+/// Observes how long a command waited for its snapshot to become available, as its own phase
+/// split out from `SCHED_PROCESSING_READ_HISTOGRAM_VEC`'s total.
+pub fn tls_collect_async_snapshot_duration(cmd: CommandKind, secs: f64) {
+    SCHED_ASYNC_SNAPSHOT_DURATION_STATIC.get(cmd).observe(secs);
+}
+
+/// Observes how long a command was queued before a scheduler worker thread started processing
+/// it.
+///
+/// When this is fed from draining a batch of queued messages, the wait time must be attributed
+/// using the timestamp captured when the *first* message in the batch was enqueued, observed
+/// exactly once per batch (e.g. via a "recorded" flag set on the first dequeue), not re-observed
+/// for later messages in the same batch -- otherwise the wait timer resets mid-batch and
+/// under/over-counts queueing delay. That batch-draining loop lives in the scheduler's message
+/// pump, which isn't vendored in this checkout; this function only provides the metric itself.
+pub fn tls_collect_wait_for_thread_duration(cmd: CommandKind, secs: f64) {
+    SCHED_WAIT_FOR_THREAD_DURATION_STATIC.get(cmd).observe(secs);
+}
+
+/// Observes how long a command spent processing before its write was handed to the engine, as
+/// its own phase split out from `SCHED_PROCESSING_WRITE_HISTOGRAM_VEC`'s total.
+pub fn tls_collect_process_before_write_duration(cmd: CommandKind, secs: f64) {
+    SCHED_PROCESS_BEFORE_WRITE_DURATION_STATIC
+        .get(cmd)
+        .observe(secs);
 }
@@ -9,15 +9,28 @@
 //! is used by the [`Server`](crate::server::Server). The [`BTreeEngine`](kv::BTreeEngine) and
 //! [`RocksEngine`](RocksEngine) are used for testing only.
 
+pub(crate) mod chunking;
 pub mod config;
+pub mod coprocessor_v2;
 pub mod errors;
+pub mod field_codec;
 pub mod kv;
 pub mod lock_manager;
+pub mod lock_observer;
 pub(crate) mod metrics;
 pub mod mvcc;
+pub mod partial_read;
+pub mod predicate;
+pub mod raw_filter;
+pub mod response_codec;
+pub mod sync_storage;
 pub mod txn;
 
 mod read_pool;
+pub mod read_pool_tuner;
+pub mod tracker;
+pub mod ttl;
+pub mod txn_client;
 mod types;
 
 pub use self::{
@@ -27,6 +40,7 @@ pub use self::{
         RocksEngine, ScanMode, Snapshot, Statistics, TestEngineBuilder,
     },
     read_pool::{build_read_pool, build_read_pool_for_test},
+    tracker::{Tracker, TrackerToken, GLOBAL_TRACKERS},
     txn::{ProcessResult, Scanner, SnapshotStore, Store},
     types::{PessimisticLockRes, PrewriteResult, SecondaryLocksStatus, StorageCallback, TxnStatus},
 };
@@ -39,16 +53,24 @@ use crate::storage::{
     lock_manager::{DummyLockManager, LockManager},
     metrics::*,
     mvcc::PointGetterBuilder,
+    partial_read::KeyStatus,
+    predicate::ScanPredicate,
+    raw_filter::RawValueFilter,
     txn::{commands::TypedCommand, scheduler::Scheduler as TxnScheduler, Command},
     types::StorageCallbackType,
 };
 use concurrency_manager::ConcurrencyManager;
 use engine_rocks::{RocksEngine as RocksEngineTmp};
-use engine_traits::{CfName, ALL_CFS, CF_DEFAULT, DATA_CFS};
+use engine_traits::{CfName, ALL_CFS, CF_DEFAULT, CF_LOCK, DATA_CFS};
 use engine_traits::{IterOptions, DATA_KEY_PREFIX_LEN};
+use futures03::channel::oneshot;
 use futures03::prelude::*;
-use kvproto::kvrpcpb::{CommandPri, Context, GetRequest, IsolationLevel, KeyRange, RawGetRequest};
+use kvproto::kvrpcpb::{
+    CommandPri, Context, GetRequest, IsolationLevel, KeyRange, LockInfo, RawGetRequest,
+    ScanDetailV2,
+};
 use raftstore::store::PdTask;
+use raftstore::store::QueryKind;
 use raftstore::store::RequestInfo;
 use raftstore::store::util::build_req_info;
 use raftstore::store::util::build_key_range;
@@ -62,6 +84,7 @@ use std::{
 use std::sync::mpsc::{self, Sender};
 use std::thread::{Builder, JoinHandle};
 use std::time::Duration;
+use tikv_util::deadline::Deadline;
 use tikv_util::time::Instant;
 use tikv_util::time::ThreadReadId;
 use tikv_util::worker::FutureScheduler;
@@ -116,6 +139,12 @@ pub struct Storage<E: Engine, L: LockManager> {
     timer: Option<Sender<bool>>,
     sender: Option<Sender<ReadStats>>,
     handle: Option<JoinHandle<()>>,
+
+    tuner_timer: Option<Sender<bool>>,
+    tuner_handle: Option<JoinHandle<()>>,
+
+    ttl_gc_timer: Option<Sender<bool>>,
+    ttl_gc_handle: Option<JoinHandle<()>>,
 }
 
 impl<E: Engine, L: LockManager> Clone for Storage<E, L> {
@@ -138,6 +167,10 @@ impl<E: Engine, L: LockManager> Clone for Storage<E, L> {
             timer: None,
             sender: self.sender.clone(),
             handle: None,
+            tuner_timer: None,
+            tuner_handle: None,
+            ttl_gc_timer: None,
+            ttl_gc_handle: None,
         }
     }
 }
@@ -163,6 +196,20 @@ impl<E: Engine, L: LockManager> Drop for Storage<E, L> {
             }
         }
 
+        if let Some(h) = self.tuner_handle.take() {
+            drop(self.tuner_timer.take());
+            if let Err(e) = h.join() {
+                error!("join read-pool-tune failed"; "err" => ?e);
+            }
+        }
+
+        if let Some(h) = self.ttl_gc_handle.take() {
+            drop(self.ttl_gc_timer.take());
+            if let Err(e) = h.join() {
+                error!("join raw-ttl-gc failed"; "err" => ?e);
+            }
+        }
+
         info!("Storage stopped.");
     }
 }
@@ -182,6 +229,22 @@ macro_rules! check_key_size {
     };
 }
 
+/// Rejects a raw write whose key falls in [`chunking`]'s reserved `#`-prefixed namespace, so an
+/// ordinary `raw_put`/`raw_batch_put`/CAS can't silently collide with (and corrupt) a stored
+/// chunk. See [`chunking::is_reserved_key`].
+macro_rules! check_not_reserved_key {
+    ($key_iter: expr, $callback: ident) => {
+        for k in $key_iter {
+            if chunking::is_reserved_key(k) {
+                $callback(Err(box_err!(
+                    "key starts with the reserved chunk-storage prefix"
+                )));
+                return Ok(());
+            }
+        }
+    };
+}
+
 impl<E: Engine, L: LockManager> Storage<E, L> {
     /// Create a `Storage` from given engine.
     pub fn from_engine<R: FlowStatsReporter>(
@@ -223,6 +286,38 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                 tikv_alloc::remove_thread_memory_accessor();
             })?;
 
+        let (tuner_tx, tuner_rx) = mpsc::channel();
+        let tune_interval = Duration::from_secs(1);
+        let mut tuner = read_pool_tuner::ReadPoolTuner::new(config.scheduler_worker_pool_size);
+        let tuner_handle = Builder::new()
+            .name(thd_name!("read-pool-tune"))
+            .spawn(move || {
+                tikv_alloc::add_thread_memory_accessor();
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    tuner_rx.recv_timeout(tune_interval)
+                {
+                    if let Some(target) = tuner.tick() {
+                        info!("read pool worker count recommendation changed"; "target" => target);
+                    }
+                }
+                tikv_alloc::remove_thread_memory_accessor();
+            })?;
+
+        let (ttl_gc_tx, ttl_gc_rx) = mpsc::channel();
+        let ttl_gc_interval = Duration::from_secs(60);
+        let ttl_gc_engine = engine.clone();
+        let ttl_gc_handle = Builder::new()
+            .name(thd_name!("raw-ttl-gc"))
+            .spawn(move || {
+                tikv_alloc::add_thread_memory_accessor();
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    ttl_gc_rx.recv_timeout(ttl_gc_interval)
+                {
+                    Self::ttl_gc_once(&ttl_gc_engine);
+                }
+                tikv_alloc::remove_thread_memory_accessor();
+            })?;
+
         Ok(Storage {
             engine,
             sched,
@@ -234,9 +329,82 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
             timer: Some(tx),
             sender: Some(sender),
             handle: Some(h),
+            tuner_timer: Some(tuner_tx),
+            tuner_handle: Some(tuner_handle),
+            ttl_gc_timer: Some(ttl_gc_tx),
+            ttl_gc_handle: Some(ttl_gc_handle),
         })
     }
 
+    /// Scans every CF for raw keys whose TTL has passed and deletes them, up to
+    /// [`TTL_GC_BATCH_LIMIT`] keys per tick so a single run can't block the thread for long.
+    /// Reads already hide expired entries on their own (see [`ttl`]); this just keeps them from
+    /// accumulating on disk forever.
+    fn ttl_gc_once(engine: &E) {
+        const TTL_GC_BATCH_LIMIT: usize = 1024;
+
+        let snapshot = match futures03::executor::block_on(Self::snapshot(engine, None, &Context::default())) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                error!("raw-ttl-gc failed to take snapshot"; "err" => ?e);
+                return;
+            }
+        };
+
+        let mut expired = Vec::new();
+        let mut statistics = Statistics::default();
+        'cfs: for cf in DATA_CFS {
+            let scan_result: Result<()> = (|| {
+                let mut cursor = snapshot.iter_cf(cf, IterOptions::default(), ScanMode::Forward)?;
+                let stats = statistics.mut_cf_statistics(cf);
+                if !cursor.seek(&Key::from_encoded(Vec::new()), stats)? {
+                    return Ok(());
+                }
+                while cursor.valid()? {
+                    let (_, expire_at) = ttl::decode(cursor.value(stats).to_owned());
+                    if expire_at.map_or(false, ttl::is_expired) {
+                        expired.push((cf, cursor.key(stats).to_owned()));
+                        if expired.len() >= TTL_GC_BATCH_LIMIT {
+                            return Ok(());
+                        }
+                    }
+                    cursor.next(stats);
+                }
+                Ok(())
+            })();
+            match scan_result {
+                Ok(()) if expired.len() >= TTL_GC_BATCH_LIMIT => break 'cfs,
+                Ok(()) => {}
+                Err(e) => error!("raw-ttl-gc failed to scan"; "cf" => cf, "err" => ?e),
+            }
+        }
+
+        if expired.is_empty() {
+            return;
+        }
+
+        let modifies = expired
+            .into_iter()
+            .map(|(cf, key)| Modify::Delete(cf, Key::from_encoded(key)))
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        let res = engine.async_write(
+            &Context::default(),
+            WriteData::from_modifies(modifies),
+            Box::new(move |(_, res): (_, kv::Result<_>)| {
+                let _ = tx.send(res);
+            }),
+        );
+        match res {
+            Ok(()) => match rx.recv() {
+                Ok(Err(e)) => error!("raw-ttl-gc failed to delete expired keys"; "err" => ?e),
+                Err(e) => error!("raw-ttl-gc write callback dropped"; "err" => ?e),
+                Ok(Ok(())) => {}
+            },
+            Err(e) => error!("raw-ttl-gc failed to schedule delete"; "err" => ?e),
+        }
+    }
+
     /// Get the underlying `Engine` of the `Storage`.
     pub fn get_engine(&self) -> E {
         self.engine.clone()
@@ -279,7 +447,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         mut ctx: Context,
         key: Key,
         start_ts: TimeStamp,
-    ) -> impl Future<Output = Result<Option<Value>>> {
+    ) -> impl Future<Output = Result<(Option<Value>, ScanDetailV2)>> {
         const CMD: CommandKind = CommandKind::get;
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
@@ -297,6 +465,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     .inc();
 
                 let command_duration = tikv_util::time::Instant::now_coarse();
+                tracker::set_tls_tracker_token();
 
                 // The bypass_locks set will be checked at most once. `TsSet::vec` is more efficient
                 // here.
@@ -313,8 +482,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     )?;
                 }
 
+                let snapshot_begin = Instant::now_coarse();
                 let snapshot =
                     Self::with_tls_engine(|engine| Self::snapshot(engine, None, &ctx)).await?;
+                tracker::with_tls_tracker(|t| t.observe_snapshot(snapshot_begin.elapsed()));
                 {
                     let begin_instant = Instant::now_coarse();
                     let mut statistics = Statistics::default();
@@ -331,16 +502,25 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                         // map storage::txn::Error -> storage::Error
                         .map_err(Error::from)
                         .map(|r| {
-                            KV_COMMAND_KEYREAD_HISTOGRAM_STATIC.get(CMD).observe(1_f64);
+                            KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
+                                .get(CMD)
+                                .get(priority_tag)
+                                .observe(1_f64);
                             r
                         });
 
                     if let Ok(key) = key.to_owned().into_raw() {
-                        let req_info = build_req_info(&key, &key, false);
+                        let mut req_info = build_req_info(&key, &key, false);
+                        req_info.query_kind = QueryKind::Get;
                         metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &statistics);
                     }
-                    metrics::tls_collect_scan_details(CMD, &statistics);
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
                     metrics::tls_collect_read_flow(ctx.get_region_id(), &statistics);
+                    read_pool_tuner::tls_collect_read_pool_stats(&statistics);
+                    tracker::with_tls_tracker(|t| {
+                        t.merge_statistics(&statistics);
+                        t.observe_process(begin_instant.elapsed());
+                    });
                     SCHED_PROCESSING_READ_HISTOGRAM_STATIC
                         .get(CMD)
                         .observe(begin_instant.elapsed_secs());
@@ -348,7 +528,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                         .get(CMD)
                         .observe(command_duration.elapsed_secs());
 
-                    result
+                    let scan_detail = tracker::remove_tls_tracker()
+                        .map(|t| t.to_scan_detail_v2())
+                        .unwrap_or_default();
+                    result.map(|v| (v, scan_detail))
                 }
             },
             priority,
@@ -366,10 +549,11 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     pub fn batch_get_command(
         &self,
         requests: Vec<GetRequest>,
-    ) -> impl Future<Output = Result<Vec<Result<Option<Vec<u8>>>>>> {
+    ) -> impl Future<Output = Result<(Vec<Result<Option<Vec<u8>>>>, ScanDetailV2)>> {
         const CMD: CommandKind = CommandKind::batch_get_command;
         // all requests in a batch have the same region, epoch, term, replica_read
         let priority = requests[0].get_context().get_priority();
+        let priority_tag = get_priority_tag(priority);
         let enable_async_commit = self.enable_async_commit;
         let concurrency_manager = self.concurrency_manager.clone();
         let res =
@@ -384,8 +568,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     KV_COMMAND_COUNTER_VEC_STATIC.get(CMD).inc();
                     KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                         .get(CMD)
+                        .get(priority_tag)
                         .observe(requests.len() as f64);
                     let command_duration = tikv_util::time::Instant::now_coarse();
+                    tracker::set_tls_tracker_token();
                     let read_id = Some(ThreadReadId::new());
                     let mut statistics = Statistics::default();
                     let mut results = Vec::default();
@@ -459,10 +645,12 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                                         if let Ok(k) = key.to_owned().into_raw() {
                                             req_info = build_req_info(&k, &k, false);
                                         }
+                                        req_info.query_kind = QueryKind::BatchGet;
                                         let v = point_getter.get(&key);
                                         let stat = point_getter.take_statistics();
                                         metrics::tls_collect_read_flow(region_id, &stat);
                                         statistics.add(&stat);
+                                        tracker::with_tls_tracker(|t| t.merge_statistics(&stat));
                                         results
                                             .push(v.map_err(|e| Error::from(txn::Error::from(e))));
                                         metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &stat);
@@ -475,11 +663,15 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                             }
                         }
                     }
-                    metrics::tls_collect_scan_details(CMD, &statistics);
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
+                    read_pool_tuner::tls_collect_read_pool_stats(&statistics);
                     SCHED_HISTOGRAM_VEC_STATIC
                         .get(CMD)
                         .observe(command_duration.elapsed_secs());
-                    Ok(results)
+                    let scan_detail = tracker::remove_tls_tracker()
+                        .map(|t| t.to_scan_detail_v2())
+                        .unwrap_or_default();
+                    Ok((results, scan_detail))
                 },
                 priority,
                 thread_rng().next_u64(),
@@ -490,6 +682,66 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         }
     }
 
+    /// Like [`batch_get_command`](Self::batch_get_command), but reports each key's outcome as a
+    /// structured [`KeyStatus`] instead of failing the whole batch on a locked key, so a caller
+    /// can decide per key whether to wait, resolve, or skip instead of pattern-matching the
+    /// `ErrorInner::Txn(..Mvcc(KeyIsLocked))` chain by hand.
+    ///
+    /// If `resolve_locks` is set, a locked key has its lock's primary checked with
+    /// `CheckTxnStatus` -- which itself rolls back an expired lock or confirms a committed one
+    /// as a side effect -- and the key is then read once more before its status is returned.
+    pub async fn batch_get_command_with_status(
+        &self,
+        requests: Vec<GetRequest>,
+        resolve_locks: bool,
+    ) -> Result<Vec<KeyStatus>> {
+        let originals = requests.clone();
+        let (raw_results, _) = self.batch_get_command(requests).await?;
+        let mut statuses: Vec<KeyStatus> =
+            raw_results.into_iter().map(KeyStatus::from_result).collect();
+
+        if resolve_locks {
+            for (req, status) in originals.iter().zip(statuses.iter_mut()) {
+                let (lock_ts, primary) = match status {
+                    KeyStatus::Locked { lock_ts, primary } => (*lock_ts, primary.clone()),
+                    _ => continue,
+                };
+                // There's no PD clock available at this layer, so the lock's own timestamp
+                // stands in for "now"; `CheckTxnStatus` still rolls back the lock if its
+                // primary is gone or confirms it if committed, which is what actually clears
+                // it for the retried read below.
+                let _ = self
+                    .check_txn_status_for_resolve(
+                        Key::from_raw(&primary),
+                        lock_ts,
+                        req.get_context().clone(),
+                    )
+                    .await;
+                let (mut retried, _) = self.batch_get_command(vec![req.clone()]).await?;
+                *status = KeyStatus::from_result(retried.remove(0));
+            }
+        }
+        Ok(statuses)
+    }
+
+    async fn check_txn_status_for_resolve(
+        &self,
+        primary_key: Key,
+        lock_ts: TimeStamp,
+        ctx: Context,
+    ) -> Result<TxnStatus> {
+        use crate::storage::txn::commands::CheckTxnStatus;
+
+        let (tx, rx) = oneshot::channel();
+        self.sched_txn_command(
+            CheckTxnStatus::new(primary_key, lock_ts, lock_ts, lock_ts, false, ctx),
+            Box::new(move |res| {
+                let _ = tx.send(res);
+            }),
+        )?;
+        rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+    }
+
     /// Get values of a set of keys in a batch from the snapshot.
     ///
     /// Only writes that are committed before `start_ts` are visible.
@@ -498,7 +750,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         mut ctx: Context,
         keys: Vec<Key>,
         start_ts: TimeStamp,
-    ) -> impl Future<Output = Result<Vec<Result<KvPair>>>> {
+    ) -> impl Future<Output = Result<(Vec<Result<KvPair>>, ScanDetailV2)>> {
         const CMD: CommandKind = CommandKind::batch_get;
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
@@ -507,6 +759,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
 
         let res = self.read_pool.spawn_handle(
             async move {
+                tracker::set_tls_tracker_token();
                 // let mut key_ranges = vec![];
                 // for key in &keys {
                 //     if let Ok(key) = key.to_owned().into_raw() {
@@ -517,7 +770,9 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                 let mut req_infos = vec![];
                 for key in &keys {
                     if let Ok(key) = key.to_owned().into_raw() {
-                        req_infos.push(build_req_info(&key, &key, false));
+                        let mut req_info = build_req_info(&key, &key, false);
+                        req_info.query_kind = QueryKind::BatchGet;
+                        req_infos.push(req_info);
                     }
                 }
 
@@ -573,20 +828,29 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                                 .collect();
                             KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                                 .get(CMD)
+                                .get(priority_tag)
                                 .observe(kv_pairs.len() as f64);
                             kv_pairs
                         });
 
                     metrics::tls_collect_req_info_batch(ctx.get_region_id(), ctx.get_peer(), req_infos, &statistics);
-                    metrics::tls_collect_scan_details(CMD, &statistics);
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
                     metrics::tls_collect_read_flow(ctx.get_region_id(), &statistics);
+                    read_pool_tuner::tls_collect_read_pool_stats(&statistics);
+                    tracker::with_tls_tracker(|t| {
+                        t.merge_statistics(&statistics);
+                        t.observe_process(begin_instant.elapsed());
+                    });
                     SCHED_PROCESSING_READ_HISTOGRAM_STATIC
                         .get(CMD)
                         .observe(begin_instant.elapsed_secs());
                     SCHED_HISTOGRAM_VEC_STATIC
                         .get(CMD)
                         .observe(command_duration.elapsed_secs());
-                    result
+                    let scan_detail = tracker::remove_tls_tracker()
+                        .map(|t| t.to_scan_detail_v2())
+                        .unwrap_or_default();
+                    result.map(|v| (v, scan_detail))
                 }
             },
             priority,
@@ -604,6 +868,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     /// If `end_key` is `None`, it means the upper bound is unbounded.
     ///
     /// Only writes committed before `start_ts` are visible.
+    ///
+    /// If `value_predicate` is given, a scanned key only counts against `limit` once its
+    /// committed value matches the predicate; keys whose value doesn't match (including ones
+    /// that fail to parse under the predicate's `Conversion`) are skipped rather than returned.
     pub fn scan(
         &self,
         mut ctx: Context,
@@ -614,6 +882,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         start_ts: TimeStamp,
         key_only: bool,
         reverse_scan: bool,
+        value_predicate: Option<ScanPredicate>,
     ) -> impl Future<Output = Result<Vec<Result<KvPair>>>> {
         const CMD: CommandKind = CommandKind::scan;
         let priority = ctx.get_priority();
@@ -632,6 +901,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                         }
                     }
                     req_info = build_req_info(&start_key, &key, reverse_scan);
+                    req_info.query_kind = QueryKind::Scan;
                     // tls_collect_qps(
                     //     ctx.get_region_id(),
                     //     ctx.get_peer(),
@@ -689,11 +959,38 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                         scanner =
                             snap_store.scanner(true, key_only, false, end_key, Some(start_key))?;
                     };
-                    let res = scanner.scan(limit, sample_step);
+                    let res = if let Some(predicate) = &value_predicate {
+                        // Filter by value before `limit` is applied, so a selective predicate
+                        // doesn't cut the scan short on rows it's about to discard anyway.
+                        let mut pairs = Vec::new();
+                        let mut visited = 0;
+                        loop {
+                            if pairs.len() >= limit {
+                                break;
+                            }
+                            match scanner.next() {
+                                Ok(Some((key, value))) => {
+                                    let sampled = sample_step <= 1 || visited % sample_step == 0;
+                                    visited += 1;
+                                    if sampled && predicate.matches(&value) {
+                                        pairs.push(Ok((key.into_raw().unwrap_or_default(), value)));
+                                    }
+                                }
+                                Ok(None) => break,
+                                Err(e) => {
+                                    pairs.push(Err(e));
+                                    break;
+                                }
+                            }
+                        }
+                        Ok(pairs)
+                    } else {
+                        scanner.scan(limit, sample_step)
+                    };
 
                     let statistics = scanner.take_statistics();
                     metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &statistics);
-                    metrics::tls_collect_scan_details(CMD, &statistics);
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
                     metrics::tls_collect_read_flow(ctx.get_region_id(), &statistics);
                     SCHED_PROCESSING_READ_HISTOGRAM_STATIC
                         .get(CMD)
@@ -705,6 +1002,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     res.map_err(Error::from).map(|results| {
                         KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                             .get(CMD)
+                            .get(priority_tag)
                             .observe(results.len() as f64);
                         results
                             .into_iter()
@@ -800,6 +1098,98 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         Ok(())
     }
 
+    /// Scans the LOCK column family directly, bypassing isolation/snapshot-store machinery,
+    /// and returns every lock in `[start_key, ..)` (up to `limit`) whose `ts <= max_ts`.
+    ///
+    /// This is much cheaper than an MVCC scan, but since it only covers a single region it
+    /// can miss locks written concurrently; pair it with
+    /// [`start_collecting_locks`](Storage::start_collecting_locks) to catch those.
+    pub fn physical_scan_lock(
+        &self,
+        ctx: Context,
+        max_ts: TimeStamp,
+        start_key: Key,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<LockInfo>>> {
+        const CMD: CommandKind = CommandKind::physical_scan_lock;
+        let priority = ctx.get_priority();
+        let priority_tag = get_priority_tag(priority);
+
+        let res = self.read_pool.spawn_handle(
+            async move {
+                KV_COMMAND_COUNTER_VEC_STATIC.get(CMD).inc();
+                SCHED_COMMANDS_PRI_COUNTER_VEC_STATIC
+                    .get(priority_tag)
+                    .inc();
+
+                let command_duration = tikv_util::time::Instant::now_coarse();
+                let snapshot =
+                    Self::with_tls_engine(|engine| Self::snapshot(engine, None, &ctx)).await?;
+                {
+                    let begin_instant = Instant::now_coarse();
+                    let mut statistics = Statistics::default();
+                    let locks =
+                        Self::scan_locks_physical(&snapshot, &start_key, max_ts, limit, &mut statistics)?;
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
+                    SCHED_PROCESSING_READ_HISTOGRAM_STATIC
+                        .get(CMD)
+                        .observe(begin_instant.elapsed_secs());
+                    SCHED_HISTOGRAM_VEC_STATIC
+                        .get(CMD)
+                        .observe(command_duration.elapsed_secs());
+                    Ok(locks)
+                }
+            },
+            priority,
+            thread_rng().next_u64(),
+        );
+        async move {
+            res.map_err(|_| Error::from(ErrorInner::SchedTooBusy))
+                .await?
+        }
+    }
+
+    fn scan_locks_physical(
+        snapshot: &E::Snap,
+        start_key: &Key,
+        max_ts: TimeStamp,
+        limit: usize,
+        statistics: &mut Statistics,
+    ) -> Result<Vec<LockInfo>> {
+        let option = IterOptions::default();
+        let mut cursor = snapshot.iter_cf(CF_LOCK, option, ScanMode::Forward)?;
+        let stats = statistics.mut_cf_statistics(CF_LOCK);
+        if !cursor.seek(start_key, stats)? {
+            return Ok(vec![]);
+        }
+        let mut locks = vec![];
+        while cursor.valid()? && locks.len() < limit {
+            let lock = Lock::parse(cursor.value(stats))?;
+            if lock.ts <= max_ts {
+                let raw_key = Key::from_encoded_slice(cursor.key(stats)).to_raw()?;
+                locks.push(lock.into_lock_info(raw_key));
+            }
+            cursor.next(stats);
+        }
+        Ok(locks)
+    }
+
+    /// Starts the process-wide [`AppliedLockCollector`](lock_observer::AppliedLockCollector)
+    /// buffering locks with `ts <= max_ts` as they are written through the apply path.
+    pub fn start_collecting_locks(&self, max_ts: TimeStamp) -> Result<()> {
+        lock_observer::APPLIED_LOCK_COLLECTOR.start_collecting(max_ts)
+    }
+
+    /// Returns the locks collected so far and whether the collector's buffer has overflowed.
+    pub fn get_collected_locks(&self) -> Result<(Vec<LockInfo>, bool)> {
+        lock_observer::APPLIED_LOCK_COLLECTOR.get_collected_locks()
+    }
+
+    /// Stops the apply-time lock collector and discards its buffer.
+    pub fn stop_collecting_locks(&self) -> Result<()> {
+        lock_observer::APPLIED_LOCK_COLLECTOR.stop_collecting()
+    }
+
     fn raw_get_key_value<S: Snapshot>(
         snapshot: &S,
         cf: String,
@@ -810,15 +1200,55 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         // no scan_count for this kind of op.
 
         let key_len = key.len();
-        snapshot
+        let value = snapshot
             .get_cf(cf, &Key::from_encoded(key))
-            .map(|value| {
-                stats.data.flow_stats.read_keys = 1;
-                stats.data.flow_stats.read_bytes =
-                    key_len + value.as_ref().map(|v| v.len()).unwrap_or(0);
-                value
-            })
-            .map_err(Error::from)
+            .map_err(Error::from)?;
+        let value = match value {
+            Some(v) => Self::decode_live_raw_value(snapshot, cf, v)?,
+            None => None,
+        };
+        stats.data.flow_stats.read_keys = 1;
+        stats.data.flow_stats.read_bytes =
+            key_len + value.as_ref().map(|v| v.len()).unwrap_or(0);
+        Ok(value)
+    }
+
+    /// Strips `value`'s [`ttl`] wrapper if it has one, treating an expired entry the same as a
+    /// missing key, then reassembles it if what's left is a [`chunking::ChunkManifest`].
+    fn decode_live_raw_value<S: Snapshot>(
+        snapshot: &S,
+        cf: CfName,
+        value: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>> {
+        let (value, expire_at) = ttl::decode(value);
+        if let Some(expire_at) = expire_at {
+            if ttl::is_expired(expire_at) {
+                return Ok(None);
+            }
+        }
+        Self::reassemble_raw_value(snapshot, cf, value).map(Some)
+    }
+
+    /// Reassembles `value` if it's a [`chunking::ChunkManifest`], otherwise returns it unchanged.
+    fn reassemble_raw_value<S: Snapshot>(
+        snapshot: &S,
+        cf: CfName,
+        value: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let manifest = match chunking::ChunkManifest::decode(&value) {
+            Some(manifest) => manifest,
+            None => return Ok(value),
+        };
+        let mut assembled = Vec::with_capacity(manifest.total_len);
+        for hash in manifest.chunk_hashes {
+            let chunk_key = Key::from_encoded(chunking::chunk_key(hash));
+            let chunk = snapshot
+                .get_cf(cf, &chunk_key)
+                .map_err(Error::from)?
+                .ok_or_else(|| box_err!("missing content-defined chunk for raw value"))?;
+            assembled.extend_from_slice(&chunk);
+        }
+        Ok(assembled)
     }
 
     /// Get the value of a raw key.
@@ -849,7 +1279,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     let begin_instant = Instant::now_coarse();
                     let mut stats = Statistics::default();
                     let r = Self::raw_get_key_value(&snapshot, cf, key, &mut stats);
-                    KV_COMMAND_KEYREAD_HISTOGRAM_STATIC.get(CMD).observe(1_f64);
+                    KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
+                        .get(CMD)
+                        .get(priority_tag)
+                        .observe(1_f64);
                     tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &stats);
                     tls_collect_read_flow(ctx.get_region_id(), &stats);
                     SCHED_PROCESSING_READ_HISTOGRAM_STATIC
@@ -871,6 +1304,96 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         }
     }
 
+    /// Query how much longer a raw key has to live. See [`ttl::TtlStatus`].
+    pub fn raw_get_key_ttl(
+        &self,
+        ctx: Context,
+        cf: String,
+        key: Vec<u8>,
+    ) -> impl Future<Output = Result<ttl::TtlStatus>> {
+        const CMD: CommandKind = CommandKind::raw_get_key_ttl;
+        let priority = ctx.get_priority();
+        let priority_tag = get_priority_tag(priority);
+
+        let res = self.read_pool.spawn_handle(
+            async move {
+                KV_COMMAND_COUNTER_VEC_STATIC.get(CMD).inc();
+                SCHED_COMMANDS_PRI_COUNTER_VEC_STATIC
+                    .get(priority_tag)
+                    .inc();
+
+                let command_duration = tikv_util::time::Instant::now_coarse();
+                let snapshot =
+                    Self::with_tls_engine(|engine| Self::snapshot(engine, None, &ctx)).await?;
+                let cf = Self::rawkv_cf(&cf)?;
+                let status = match snapshot
+                    .get_cf(cf, &Key::from_encoded(key))
+                    .map_err(Error::from)?
+                {
+                    None => ttl::TtlStatus::NotFound,
+                    Some(value) => match ttl::decode(value).1 {
+                        None => ttl::TtlStatus::NoExpire,
+                        Some(expire_at) if ttl::is_expired(expire_at) => ttl::TtlStatus::NotFound,
+                        Some(expire_at) => ttl::TtlStatus::ExpiresIn(ttl::remaining_secs(expire_at)),
+                    },
+                };
+                SCHED_HISTOGRAM_VEC_STATIC
+                    .get(CMD)
+                    .observe(command_duration.elapsed_secs());
+                Ok(status)
+            },
+            priority,
+            thread_rng().next_u64(),
+        );
+
+        async move {
+            res.map_err(|_| Error::from(ErrorInner::SchedTooBusy))
+                .await?
+        }
+    }
+
+    /// Reads a single field out of a structured raw value without returning the rest of it. See
+    /// [`field_codec`] for the value layout and `path` semantics.
+    pub fn raw_get_field(
+        &self,
+        ctx: Context,
+        cf: String,
+        key: Vec<u8>,
+        path: Vec<Vec<u8>>,
+    ) -> impl Future<Output = Result<Vec<u8>>> {
+        const CMD: CommandKind = CommandKind::raw_get_field;
+        let priority = ctx.get_priority();
+        let priority_tag = get_priority_tag(priority);
+
+        let res = self.read_pool.spawn_handle(
+            async move {
+                KV_COMMAND_COUNTER_VEC_STATIC.get(CMD).inc();
+                SCHED_COMMANDS_PRI_COUNTER_VEC_STATIC
+                    .get(priority_tag)
+                    .inc();
+
+                let command_duration = tikv_util::time::Instant::now_coarse();
+                let snapshot =
+                    Self::with_tls_engine(|engine| Self::snapshot(engine, None, &ctx)).await?;
+                let mut stats = Statistics::default();
+                let value = Self::raw_get_key_value(&snapshot, cf, key, &mut stats)?
+                    .ok_or_else(|| box_err!("key not found"))?;
+                let field = field_codec::get_field(&value, &path).map_err(|e| box_err!("{}", e))?;
+                SCHED_HISTOGRAM_VEC_STATIC
+                    .get(CMD)
+                    .observe(command_duration.elapsed_secs());
+                Ok(field)
+            },
+            priority,
+            thread_rng().next_u64(),
+        );
+
+        async move {
+            res.map_err(|_| Error::from(ErrorInner::SchedTooBusy))
+                .await?
+        }
+    }
+
     /// Get the values of a set of raw keys, return a list of `Result`s.
     pub fn raw_batch_get_command(
         &self,
@@ -894,6 +1417,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     .inc();
                 KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                     .get(CMD)
+                    .get(priority_tag)
                     .observe(gets.len() as f64);
                 let command_duration = tikv_util::time::Instant::now_coarse();
                 let read_id = Some(ThreadReadId::new());
@@ -942,12 +1466,23 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         }
     }
 
-    /// Get the values of some raw keys in a batch.
+    /// Get the values of some raw keys in a batch, all from `cf`.
     pub fn raw_batch_get(
         &self,
         ctx: Context,
         cf: String,
         keys: Vec<Vec<u8>>,
+    ) -> impl Future<Output = Result<Vec<Result<KvPair>>>> {
+        self.raw_batch_get_cf(ctx, keys.into_iter().map(|k| (cf.clone(), k)).collect())
+    }
+
+    /// Get the values of some raw keys in a batch, each carrying its own column family. This
+    /// lets a single batch span several CFs off of one snapshot instead of one RPC per CF, while
+    /// preserving the `keys` ordering in the result.
+    pub fn raw_batch_get_cf(
+        &self,
+        ctx: Context,
+        keys: Vec<(String, Vec<u8>)>,
     ) -> impl Future<Output = Result<Vec<Result<KvPair>>>> {
         const CMD: CommandKind = CommandKind::raw_batch_get;
         let priority = ctx.get_priority();
@@ -955,13 +1490,8 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
 
         let res = self.read_pool.spawn_handle(
             async move {
-                // let mut key_ranges = vec![];
-                // for key in &keys {
-                //     key_ranges.push(build_key_range(key, key, false));
-                // }
-                // tls_collect_qps_batch(ctx.get_region_id(), ctx.get_peer(), key_ranges);
                 let mut req_infos = vec![];
-                for key in &keys {
+                for (_, key) in &keys {
                     req_infos.push(build_req_info(&key, &key, false));
                 }
 
@@ -975,30 +1505,32 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     Self::with_tls_engine(|engine| Self::snapshot(engine, None, &ctx)).await?;
                 {
                     let begin_instant = Instant::now_coarse();
-                    let keys: Vec<Key> = keys.into_iter().map(Key::from_encoded).collect();
-                    let cf = Self::rawkv_cf(&cf)?;
+                    let mut resolved = Vec::with_capacity(keys.len());
+                    for (cf, key) in keys {
+                        resolved.push((Self::rawkv_cf(&cf)?, Key::from_encoded(key)));
+                    }
                     // no scan_count for this kind of op.
                     let mut stats = Statistics::default();
-                    let result: Vec<Result<KvPair>> = keys
-                        .into_iter()
-                        .map(|k| {
-                            let v = snapshot.get_cf(cf, &k);
-                            (k, v)
-                        })
-                        .filter(|&(_, ref v)| !(v.is_ok() && v.as_ref().unwrap().is_none()))
-                        .map(|(k, v)| match v {
+                    let mut result: Vec<Result<KvPair>> = Vec::with_capacity(resolved.len());
+                    for (cf, k) in resolved {
+                        match snapshot.get_cf(cf, &k) {
                             Ok(Some(v)) => {
+                                let (v, expire_at) = ttl::decode(v);
+                                if expire_at.map_or(false, ttl::is_expired) {
+                                    continue;
+                                }
                                 stats.data.flow_stats.read_keys += 1;
                                 stats.data.flow_stats.read_bytes += k.as_encoded().len() + v.len();
-                                Ok((k.into_encoded(), v))
+                                result.push(Ok((k.into_encoded(), v)));
                             }
-                            Err(e) => Err(Error::from(e)),
-                            _ => unreachable!(),
-                        })
-                        .collect();
+                            Ok(None) => {}
+                            Err(e) => result.push(Err(Error::from(e))),
+                        }
+                    }
 
                     KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                         .get(CMD)
+                        .get(priority_tag)
                         .observe(stats.data.flow_stats.read_keys as f64);
                     tls_collect_req_info_batch(ctx.get_region_id(), ctx.get_peer(), req_infos, &stats);
                     tls_collect_read_flow(ctx.get_region_id(), &stats);
@@ -1021,69 +1553,260 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         }
     }
 
-    /// Write a raw key to the storage.
-    pub fn raw_put(
+    /// Like [`raw_batch_get`](Self::raw_batch_get), but returns one [`response_codec`]-encoded
+    /// buffer instead of a `Vec<Result<KvPair>>`, so a client asking for
+    /// [`ResponseCodec::Flexbuffers`](response_codec::ResponseCodec::Flexbuffers) can index into
+    /// the response without allocating a value per key. Unlike `raw_batch_get`, a key that isn't
+    /// found is kept in the output (as a `found: false` row) rather than dropped, since the
+    /// encoded layout needs one row per requested key to stay randomly-accessible.
+    pub fn raw_batch_get_encoded(
         &self,
         ctx: Context,
         cf: String,
-        key: Vec<u8>,
-        value: Vec<u8>,
-        callback: Callback<()>,
-    ) -> Result<()> {
-        check_key_size!(Some(&key).into_iter(), self.max_key_size, callback);
+        keys: Vec<Vec<u8>>,
+        codec: response_codec::ResponseCodec,
+    ) -> impl Future<Output = Result<Vec<u8>>> {
+        const CMD: CommandKind = CommandKind::raw_batch_get_encoded;
+        let priority = ctx.get_priority();
+        let priority_tag = get_priority_tag(priority);
 
-        let kv_size = key.len() + value.len();
-        let req_info = build_req_info(&key, &key, false);
+        let res = self.read_pool.spawn_handle(
+            async move {
+                let mut req_infos = vec![];
+                for key in &keys {
+                    req_infos.push(build_req_info(key, key, false));
+                }
 
-        self.engine.async_write(
-            &ctx,
-            WriteData::from_modifies(vec![Modify::Put(
-                Self::rawkv_cf(&cf)?,
-                Key::from_encoded(key),
-                value,
-            )]),
-            Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
-        )?;
+                KV_COMMAND_COUNTER_VEC_STATIC.get(CMD).inc();
+                SCHED_COMMANDS_PRI_COUNTER_VEC_STATIC
+                    .get(priority_tag)
+                    .inc();
 
-        tls_collect_write_req_info(&self.sender, ctx.get_region_id(), ctx.get_peer(), req_info, kv_size);
+                let command_duration = tikv_util::time::Instant::now_coarse();
+                let snapshot =
+                    Self::with_tls_engine(|engine| Self::snapshot(engine, None, &ctx)).await?;
+                let cf = Self::rawkv_cf(&cf)?;
+                let begin_instant = Instant::now_coarse();
+                let mut stats = Statistics::default();
+                let mut rows: Vec<response_codec::BatchGetRow> = Vec::with_capacity(keys.len());
+                for key in keys {
+                    let encoded_key = Key::from_encoded(key.clone());
+                    let value = match snapshot.get_cf(cf, &encoded_key) {
+                        Ok(Some(v)) => Self::decode_live_raw_value(&snapshot, cf, v)?,
+                        Ok(None) => None,
+                        Err(e) => return Err(Error::from(e)),
+                    };
+                    if let Some(value) = &value {
+                        stats.data.flow_stats.read_keys += 1;
+                        stats.data.flow_stats.read_bytes += key.len() + value.len();
+                    }
+                    rows.push((key, value));
+                }
 
-        KV_COMMAND_COUNTER_VEC_STATIC.raw_put.inc();
-        Ok(())
+                KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
+                    .get(CMD)
+                    .get(priority_tag)
+                    .observe(stats.data.flow_stats.read_keys as f64);
+                tls_collect_req_info_batch(ctx.get_region_id(), ctx.get_peer(), req_infos, &stats);
+                tls_collect_read_flow(ctx.get_region_id(), &stats);
+                SCHED_PROCESSING_READ_HISTOGRAM_STATIC
+                    .get(CMD)
+                    .observe(begin_instant.elapsed_secs());
+                SCHED_HISTOGRAM_VEC_STATIC
+                    .get(CMD)
+                    .observe(command_duration.elapsed_secs());
+                Ok(response_codec::encode(&rows, codec))
+            },
+            priority,
+            thread_rng().next_u64(),
+        );
+
+        async move {
+            res.map_err(|_| Error::from(ErrorInner::SchedTooBusy))
+                .await?
+        }
+    }
+
+    /// Builds a [`Deadline`] from `ctx`'s `max_execution_duration_ms`, if set. A raw request
+    /// with no deadline configured runs unbounded, matching the behavior before deadlines
+    /// were enforced on this path.
+    fn deadline_from_ctx(ctx: &Context) -> Option<Deadline> {
+        let execution_duration_ms = ctx.get_max_execution_duration_ms();
+        if execution_duration_ms == 0 {
+            None
+        } else {
+            Some(Deadline::from_now(Duration::from_millis(
+                execution_duration_ms,
+            )))
+        }
     }
 
-    /// Write some keys to the storage in a batch.
+    /// Spawns a raw modify `future` on the scheduler's worker pool, giving raw commands the
+    /// same admission control the txn path already gets from `sched_txn_command`. Returns
+    /// `SchedTooBusy` instead of queuing when the pool is saturated.
+    fn sched_raw_command<F>(&self, tag: CommandKind, future: F) -> Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        KV_COMMAND_COUNTER_VEC_STATIC.get(tag).inc();
+        self.sched
+            .get_sched_pool(CommandPri::Normal)
+            .pool
+            .spawn(future)
+            .map_err(|_| Error::from(ErrorInner::SchedTooBusy))
+    }
+
+    /// Write a raw key to the storage. `ttl`, if given, is the number of seconds until the key
+    /// expires; [`raw_get`](Self::raw_get) and the raw scans stop returning it once that time
+    /// passes. `None` stores `value` exactly as before TTL existed, so it never expires.
+    pub fn raw_put(
+        &self,
+        ctx: Context,
+        cf: String,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        ttl: Option<u64>,
+        callback: Callback<()>,
+    ) -> Result<()> {
+        check_key_size!(Some(&key).into_iter(), self.max_key_size, callback);
+        check_not_reserved_key!(Some(&key).into_iter(), callback);
+
+        let cf = Self::rawkv_cf(&cf)?;
+        let deadline = Self::deadline_from_ctx(&ctx);
+        let engine = self.engine.clone();
+        let sender = self.sender.clone();
+
+        self.sched_raw_command(CommandKind::raw_put, async move {
+            if let Some(deadline) = deadline {
+                if let Err(e) = deadline.check() {
+                    callback(Err(Error::from(e)));
+                    return;
+                }
+            }
+
+            let kv_size = key.len() + value.len();
+            let req_info = build_req_info(&key, &key, false);
+            let region_id = ctx.get_region_id();
+            let peer = ctx.get_peer().clone();
+
+            let modifies = if value.len() > chunking::CDC_VALUE_THRESHOLD {
+                let chunked = chunking::chunk_value(value);
+                let mut modifies = Vec::with_capacity(chunked.chunk_writes.len() + 1);
+                modifies.extend(
+                    chunked
+                        .chunk_writes
+                        .into_iter()
+                        .map(|(k, v)| Modify::Put(cf, Key::from_encoded(k), v)),
+                );
+                let head = match ttl {
+                    Some(ttl) => ttl::encode(chunked.manifest, ttl),
+                    None => chunked.manifest,
+                };
+                modifies.push(Modify::Put(cf, Key::from_encoded(key), head));
+                modifies
+            } else {
+                let value = match ttl {
+                    Some(ttl) => ttl::encode(value, ttl),
+                    None => value,
+                };
+                vec![Modify::Put(cf, Key::from_encoded(key), value)]
+            };
+
+            let res = engine.async_write(
+                &ctx,
+                WriteData::from_modifies(modifies),
+                Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
+            );
+            match res {
+                Ok(()) => tls_collect_write_req_info(&sender, region_id, &peer, req_info, kv_size),
+                Err(e) => error!("raw_put failed to schedule write"; "err" => ?e),
+            }
+        })
+    }
+
+    /// Write some keys to the storage in a batch, all to `cf`. `ttl` behaves as in
+    /// [`raw_put`](Self::raw_put) and, if given, applies to every pair in the batch.
     pub fn raw_batch_put(
         &self,
         ctx: Context,
         cf: String,
         pairs: Vec<KvPair>,
+        ttl: Option<u64>,
         callback: Callback<()>,
     ) -> Result<()> {
-        let cf = Self::rawkv_cf(&cf)?;
+        self.raw_batch_put_cf(
+            ctx,
+            pairs.into_iter().map(|kv| (cf.clone(), kv)).collect(),
+            ttl,
+            callback,
+        )
+    }
 
+    /// Write some keys to the storage in a batch, each carrying its own column family. This lets
+    /// a single batch span several CFs in one atomic write instead of one RPC per CF. `ttl`
+    /// behaves as in [`raw_put`](Self::raw_put) and, if given, applies to every pair.
+    pub fn raw_batch_put_cf(
+        &self,
+        ctx: Context,
+        pairs: Vec<(String, KvPair)>,
+        ttl: Option<u64>,
+        callback: Callback<()>,
+    ) -> Result<()> {
         check_key_size!(
-            pairs.iter().map(|(ref k, _)| k),
+            pairs.iter().map(|(_, (ref k, _))| k),
             self.max_key_size,
             callback
         );
+        check_not_reserved_key!(pairs.iter().map(|(_, (ref k, _))| k), callback);
 
-        for (key, value) in &pairs {
-            let req_info = build_req_info(&key, &key, false);
-            let kv_size = key.len() + value.len();
-            tls_collect_write_req_info(&self.sender, ctx.get_region_id(), ctx.get_peer(), req_info, kv_size);
+        let mut resolved = Vec::with_capacity(pairs.len());
+        for (cf, kv) in pairs {
+            resolved.push((Self::rawkv_cf(&cf)?, kv));
         }
 
-        let modifies = pairs
-            .into_iter()
-            .map(|(k, v)| Modify::Put(cf, Key::from_encoded(k), v))
-            .collect();
-        self.engine.async_write(
-            &ctx,
-            WriteData::from_modifies(modifies),
-            Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
-        )?;
-        KV_COMMAND_COUNTER_VEC_STATIC.raw_batch_put.inc();
-        Ok(())
+        let deadline = Self::deadline_from_ctx(&ctx);
+        let engine = self.engine.clone();
+        let sender = self.sender.clone();
+
+        self.sched_raw_command(CommandKind::raw_batch_put, async move {
+            if let Some(deadline) = deadline {
+                if let Err(e) = deadline.check() {
+                    callback(Err(Error::from(e)));
+                    return;
+                }
+            }
+
+            let region_id = ctx.get_region_id();
+            let peer = ctx.get_peer().clone();
+            let mut req_infos = Vec::with_capacity(resolved.len());
+            for (_, (key, value)) in &resolved {
+                req_infos.push((build_req_info(key, key, false), key.len() + value.len()));
+            }
+
+            let modifies = resolved
+                .into_iter()
+                .map(|(cf, (k, v))| {
+                    let v = match ttl {
+                        Some(ttl) => ttl::encode(v, ttl),
+                        None => v,
+                    };
+                    Modify::Put(cf, Key::from_encoded(k), v)
+                })
+                .collect();
+            let res = engine.async_write(
+                &ctx,
+                WriteData::from_modifies(modifies),
+                Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
+            );
+            match res {
+                Ok(()) => {
+                    for (req_info, kv_size) in req_infos {
+                        tls_collect_write_req_info(&sender, region_id, &peer, req_info, kv_size);
+                    }
+                }
+                Err(e) => error!("raw_batch_put_cf failed to schedule write"; "err" => ?e),
+            }
+        })
     }
 
     /// Delete a raw key from the storage.
@@ -1096,16 +1819,27 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     ) -> Result<()> {
         check_key_size!(Some(&key).into_iter(), self.max_key_size, callback);
 
-        self.engine.async_write(
-            &ctx,
-            WriteData::from_modifies(vec![Modify::Delete(
-                Self::rawkv_cf(&cf)?,
-                Key::from_encoded(key),
-            )]),
-            Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
-        )?;
-        KV_COMMAND_COUNTER_VEC_STATIC.raw_delete.inc();
-        Ok(())
+        let cf = Self::rawkv_cf(&cf)?;
+        let deadline = Self::deadline_from_ctx(&ctx);
+        let engine = self.engine.clone();
+
+        self.sched_raw_command(CommandKind::raw_delete, async move {
+            if let Some(deadline) = deadline {
+                if let Err(e) = deadline.check() {
+                    callback(Err(Error::from(e)));
+                    return;
+                }
+            }
+
+            let res = engine.async_write(
+                &ctx,
+                WriteData::from_modifies(vec![Modify::Delete(cf, Key::from_encoded(key))]),
+                Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
+            );
+            if let Err(e) = res {
+                error!("raw_delete failed to schedule write"; "err" => ?e);
+            }
+        })
     }
 
     /// Delete all raw keys in [`start_key`, `end_key`).
@@ -1126,16 +1860,28 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         );
 
         let cf = Self::rawkv_cf(&cf)?;
-        let start_key = Key::from_encoded(start_key);
-        let end_key = Key::from_encoded(end_key);
+        let deadline = Self::deadline_from_ctx(&ctx);
+        let engine = self.engine.clone();
+
+        self.sched_raw_command(CommandKind::raw_delete_range, async move {
+            if let Some(deadline) = deadline {
+                if let Err(e) = deadline.check() {
+                    callback(Err(Error::from(e)));
+                    return;
+                }
+            }
 
-        self.engine.async_write(
-            &ctx,
-            WriteData::from_modifies(vec![Modify::DeleteRange(cf, start_key, end_key, false)]),
-            Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
-        )?;
-        KV_COMMAND_COUNTER_VEC_STATIC.raw_delete_range.inc();
-        Ok(())
+            let start_key = Key::from_encoded(start_key);
+            let end_key = Key::from_encoded(end_key);
+            let res = engine.async_write(
+                &ctx,
+                WriteData::from_modifies(vec![Modify::DeleteRange(cf, start_key, end_key, false)]),
+                Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
+            );
+            if let Err(e) = res {
+                error!("raw_delete_range failed to schedule write"; "err" => ?e);
+            }
+        })
     }
 
     /// Delete some raw keys in a batch.
@@ -1149,17 +1895,196 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         let cf = Self::rawkv_cf(&cf)?;
         check_key_size!(keys.iter(), self.max_key_size, callback);
 
-        let modifies = keys
-            .into_iter()
-            .map(|k| Modify::Delete(cf, Key::from_encoded(k)))
-            .collect();
-        self.engine.async_write(
-            &ctx,
+        let deadline = Self::deadline_from_ctx(&ctx);
+        let engine = self.engine.clone();
+
+        self.sched_raw_command(CommandKind::raw_batch_delete, async move {
+            if let Some(deadline) = deadline {
+                if let Err(e) = deadline.check() {
+                    callback(Err(Error::from(e)));
+                    return;
+                }
+            }
+
+            let modifies = keys
+                .into_iter()
+                .map(|k| Modify::Delete(cf, Key::from_encoded(k)))
+                .collect();
+            let res = engine.async_write(
+                &ctx,
+                WriteData::from_modifies(modifies),
+                Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
+            );
+            if let Err(e) = res {
+                error!("raw_batch_delete failed to schedule write"; "err" => ?e);
+            }
+        })
+    }
+
+    /// Atomically compares `key`'s current value in `cf` against `previous_value` (`None`
+    /// meaning "must not exist") and, if it matches, writes `new_value`. Returns the value
+    /// observed before the swap and whether the swap took place.
+    ///
+    /// The read-check-write holds `key`'s entry in the concurrency manager's lock table for the
+    /// duration -- the same table [`scan`](Storage::scan) already consults for txn reads --
+    /// serializing this call against other `raw_compare_and_swap`/`raw_batch_atomic` calls on
+    /// the same key. This closes the lost-update gap of a plain read-then-`raw_put` sequence and
+    /// is the primitive needed for optimistic locking on raw KV.
+    pub fn raw_compare_and_swap(
+        &self,
+        ctx: Context,
+        cf: String,
+        key: Vec<u8>,
+        previous_value: Option<Vec<u8>>,
+        new_value: Vec<u8>,
+    ) -> impl Future<Output = Result<(Option<Vec<u8>>, bool)>> {
+        let engine = self.engine.clone();
+        let concurrency_manager = self.concurrency_manager.clone();
+        let (tx, rx) = oneshot::channel();
+
+        let spawn_res = self.sched_raw_command(CommandKind::raw_compare_and_swap, async move {
+            let result = Self::do_raw_batch_atomic(
+                &engine,
+                &concurrency_manager,
+                &ctx,
+                cf,
+                vec![(key, previous_value, new_value)],
+            )
+            .await
+            .map(|(mut observed, applied)| (observed.pop().unwrap(), applied));
+            let _ = tx.send(result);
+        });
+
+        async move {
+            spawn_res?;
+            rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+        }
+    }
+
+    /// Like [`raw_compare_and_swap`](Storage::raw_compare_and_swap), applied to several keys
+    /// all-or-nothing: every `(key, previous_value, new_value)` triple's observed value must
+    /// match `previous_value` before any of the writes happen.
+    pub fn raw_batch_atomic(
+        &self,
+        ctx: Context,
+        cf: String,
+        triples: Vec<(Vec<u8>, Option<Vec<u8>>, Vec<u8>)>,
+    ) -> impl Future<Output = Result<(Vec<Option<Vec<u8>>>, bool)>> {
+        let engine = self.engine.clone();
+        let concurrency_manager = self.concurrency_manager.clone();
+        let (tx, rx) = oneshot::channel();
+
+        let spawn_res = self.sched_raw_command(CommandKind::raw_batch_atomic, async move {
+            let result =
+                Self::do_raw_batch_atomic(&engine, &concurrency_manager, &ctx, cf, triples).await;
+            let _ = tx.send(result);
+        });
+
+        async move {
+            spawn_res?;
+            rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+        }
+    }
+
+    /// Locks every key in `triples` (in sorted order, so two overlapping batches can't deadlock
+    /// on each other), reads each one's current value in `cf`, and -- only if every value
+    /// matches its expected `previous_value` -- applies every `new_value` as one atomic write.
+    /// Returns the value observed for each key (before any write) and whether the batch was
+    /// applied.
+    async fn do_raw_batch_atomic(
+        engine: &E,
+        concurrency_manager: &ConcurrencyManager,
+        ctx: &Context,
+        cf: String,
+        triples: Vec<(Vec<u8>, Option<Vec<u8>>, Vec<u8>)>,
+    ) -> Result<(Vec<Option<Vec<u8>>>, bool)> {
+        for (key, ..) in &triples {
+            if chunking::is_reserved_key(key) {
+                return Err(box_err!("key starts with the reserved chunk-storage prefix"));
+            }
+        }
+
+        let mut lock_order: Vec<usize> = (0..triples.len()).collect();
+        lock_order.sort_by(|&a, &b| triples[a].0.cmp(&triples[b].0));
+
+        let mut guards = Vec::with_capacity(triples.len());
+        for i in lock_order {
+            let key = Key::from_encoded(triples[i].0.clone());
+            guards.push(concurrency_manager.lock_key(&key).await);
+        }
+
+        let cf_name = Self::rawkv_cf(&cf)?;
+        let snapshot = Self::snapshot(engine, None, ctx).await?;
+        let mut stats = Statistics::default();
+        let mut observed = Vec::with_capacity(triples.len());
+        let mut all_match = true;
+        for (key, previous_value, _) in &triples {
+            let current = Self::raw_get_key_value(&snapshot, cf.clone(), key.clone(), &mut stats)?;
+            if &current != previous_value {
+                all_match = false;
+            }
+            observed.push(current);
+        }
+
+        if !all_match {
+            return Ok((observed, false));
+        }
+
+        let mut modifies = Vec::with_capacity(triples.len());
+        for (key, _, new_value) in triples {
+            if new_value.len() > chunking::CDC_VALUE_THRESHOLD {
+                let chunked = chunking::chunk_value(new_value);
+                modifies.extend(
+                    chunked
+                        .chunk_writes
+                        .into_iter()
+                        .map(|(k, v)| Modify::Put(cf_name, Key::from_encoded(k), v)),
+                );
+                modifies.push(Modify::Put(cf_name, Key::from_encoded(key), chunked.manifest));
+            } else {
+                modifies.push(Modify::Put(cf_name, Key::from_encoded(key), new_value));
+            }
+        }
+        Self::write_modifies(engine, ctx, modifies).await?;
+        Ok((observed, true))
+    }
+
+    /// Applies `modifies` and resolves once the write completes, bridging the callback-based
+    /// engine write API to a future so [`do_raw_batch_atomic`](Storage::do_raw_batch_atomic) can
+    /// await its own write before releasing the keys' lock guards.
+    fn write_modifies(
+        engine: &E,
+        ctx: &Context,
+        modifies: Vec<Modify>,
+    ) -> impl Future<Output = Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let res = engine.async_write(
+            ctx,
             WriteData::from_modifies(modifies),
-            Box::new(|(_, res): (_, kv::Result<_>)| callback(res.map_err(Error::from))),
-        )?;
-        KV_COMMAND_COUNTER_VEC_STATIC.raw_batch_delete.inc();
-        Ok(())
+            Box::new(move |(_, res): (_, kv::Result<()>)| {
+                let _ = tx.send(res.map_err(Error::from));
+            }),
+        );
+        async move {
+            res?;
+            rx.await.map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+        }
+    }
+
+    /// Applies a raw scan's `projection` (a list of top-level field names) to one decoded
+    /// value, when one was requested. `key_only` already strips the value down to nothing, so a
+    /// projection is only meaningful when it's false; a projection error is folded into the
+    /// pair's own `Result` rather than failing the whole scan, same as a `decode_live_raw_value`
+    /// error.
+    fn project_raw_value(
+        value: Vec<u8>,
+        key_only: bool,
+        projection: &[Vec<u8>],
+    ) -> Result<Vec<u8>> {
+        if key_only || projection.is_empty() {
+            return Ok(if key_only { vec![] } else { value });
+        }
+        field_codec::project(&value, projection).map_err(|e| box_err!("{}", e))
     }
 
     /// Scan raw keys in [`start_key`, `end_key`), returns at most `limit` keys. If `end_key` is
@@ -1167,6 +2092,33 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     ///
     /// If `key_only` is true, the value corresponding to the key will not be read. Only scanned
     /// keys will be returned.
+    /// (Each value is still read from the cursor so its TTL can be checked; a `key_only` scan
+    /// returns it empty.)
+    /// `sample_step`, when non-zero, emits only every `sample_step`-th key the cursor visits
+    /// (the cursor still advances over every key); `limit` then counts emitted keys rather than
+    /// visited ones. A `sample_step` of 0 or 1 emits every key, matching the old behavior.
+    ///
+    /// `filter` is evaluated against each decoded value before it's counted against `limit`; a
+    /// value `filter` rejects is skipped without being pushed into the result or ending the scan,
+    /// same as an entry `sample_step` skips. [`RawValueFilter::None`] matches everything,
+    /// preserving the old no-filter behavior exactly.
+
+    /// Applies a raw scan's `projection` (a list of top-level field names) to one decoded
+    /// value, when one was requested. `key_only` already strips the value down to nothing, so a
+    /// projection is only meaningful when it's false; a projection error is folded into the
+    /// pair's own `Result` rather than failing the whole scan, same as a `decode_live_raw_value`
+    /// error.
+    fn project_raw_value(
+        value: Vec<u8>,
+        key_only: bool,
+        projection: &[Vec<u8>],
+    ) -> Result<Vec<u8>> {
+        if key_only || projection.is_empty() {
+            return Ok(if key_only { vec![] } else { value });
+        }
+        field_codec::project(&value, projection).map_err(|e| box_err!("{}", e))
+    }
+
     fn forward_raw_scan(
         snapshot: &E::Snap,
         cf: &str,
@@ -1175,29 +2127,46 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         limit: usize,
         statistics: &mut Statistics,
         key_only: bool,
+        sample_step: usize,
+        projection: &[Vec<u8>],
+        filter: &RawValueFilter,
     ) -> Result<Vec<Result<KvPair>>> {
         let mut option = IterOptions::default();
         if let Some(end) = end_key {
             option.set_upper_bound(end.as_encoded(), DATA_KEY_PREFIX_LEN);
         }
-        if key_only {
-            option.set_key_only(key_only);
-        }
-        let mut cursor = snapshot.iter_cf(Self::rawkv_cf(cf)?, option, ScanMode::Forward)?;
+        // Values are always read, even for a `key_only` scan, so an expired entry's TTL can
+        // still be checked and the entry skipped rather than returned.
+        let raw_cf = Self::rawkv_cf(cf)?;
+        let mut cursor = snapshot.iter_cf(raw_cf, option, ScanMode::Forward)?;
         let statistics = statistics.mut_cf_statistics(cf);
         if !cursor.seek(start_key, statistics)? {
             return Ok(vec![]);
         }
         let mut pairs = vec![];
+        let mut visited = 0;
         while cursor.valid()? && pairs.len() < limit {
-            pairs.push(Ok((
-                cursor.key(statistics).to_owned(),
-                if key_only {
-                    vec![]
-                } else {
-                    cursor.value(statistics).to_owned()
-                },
-            )));
+            let sampled = sample_step <= 1 || visited % sample_step == 0;
+            visited += 1;
+            if !sampled {
+                cursor.next(statistics);
+                continue;
+            }
+            let key = cursor.key(statistics).to_owned();
+            if chunking::is_reserved_key(&key) {
+                // Internal chunk content, not an ordinary raw row -- never counted against
+                // `limit` or handed back to the caller.
+                cursor.next(statistics);
+                continue;
+            }
+            let raw_value = cursor.value(statistics).to_owned();
+            match Self::decode_live_raw_value(snapshot, raw_cf, raw_value) {
+                Ok(Some(value)) if filter.matches(&value) => {
+                    pairs.push(Self::project_raw_value(value, key_only, projection).map(|v| (key, v)))
+                }
+                Ok(Some(_)) | Ok(None) => {}
+                Err(e) => pairs.push(Err(e)),
+            }
             cursor.next(statistics);
         }
         Ok(pairs)
@@ -1208,6 +2177,11 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     ///
     /// If `key_only` is true, the value
     /// corresponding to the key will not be read out. Only scanned keys will be returned.
+    ///
+    /// Each value is still read from the cursor so its TTL can be checked; a `key_only` scan
+    /// returns it empty.
+    ///
+    /// `sample_step` and `filter` behave as in [`forward_raw_scan`](Storage::forward_raw_scan).
     fn reverse_raw_scan(
         snapshot: &E::Snap,
         cf: &str,
@@ -1216,34 +2190,171 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         limit: usize,
         statistics: &mut Statistics,
         key_only: bool,
+        sample_step: usize,
+        projection: &[Vec<u8>],
+        filter: &RawValueFilter,
     ) -> Result<Vec<Result<KvPair>>> {
         let mut option = IterOptions::default();
         if let Some(end) = end_key {
             option.set_lower_bound(end.as_encoded(), DATA_KEY_PREFIX_LEN);
         }
-        if key_only {
-            option.set_key_only(key_only);
-        }
-        let mut cursor = snapshot.iter_cf(Self::rawkv_cf(cf)?, option, ScanMode::Backward)?;
+        // Values are always read, even for a `key_only` scan, so an expired entry's TTL can
+        // still be checked and the entry skipped rather than returned.
+        let raw_cf = Self::rawkv_cf(cf)?;
+        let mut cursor = snapshot.iter_cf(raw_cf, option, ScanMode::Backward)?;
         let statistics = statistics.mut_cf_statistics(cf);
         if !cursor.reverse_seek(start_key, statistics)? {
             return Ok(vec![]);
         }
         let mut pairs = vec![];
+        let mut visited = 0;
         while cursor.valid()? && pairs.len() < limit {
-            pairs.push(Ok((
-                cursor.key(statistics).to_owned(),
-                if key_only {
-                    vec![]
-                } else {
-                    cursor.value(statistics).to_owned()
-                },
-            )));
+            let sampled = sample_step <= 1 || visited % sample_step == 0;
+            visited += 1;
+            if !sampled {
+                cursor.prev(statistics);
+                continue;
+            }
+            let key = cursor.key(statistics).to_owned();
+            if chunking::is_reserved_key(&key) {
+                // Internal chunk content, not an ordinary raw row -- never counted against
+                // `limit` or handed back to the caller.
+                cursor.prev(statistics);
+                continue;
+            }
+            let raw_value = cursor.value(statistics).to_owned();
+            match Self::decode_live_raw_value(snapshot, raw_cf, raw_value) {
+                Ok(Some(value)) if filter.matches(&value) => {
+                    pairs.push(Self::project_raw_value(value, key_only, projection).map(|v| (key, v)))
+                }
+                Ok(Some(_)) | Ok(None) => {}
+                Err(e) => pairs.push(Err(e)),
+            }
             cursor.prev(statistics);
         }
         Ok(pairs)
     }
 
+    /// `true` when every `ranges[i]` starts strictly ahead of `ranges[i + 1]` in scan order,
+    /// an order `check_key_ranges` itself enforces only between a range with an empty
+    /// `end_key` and the range after it. A single cursor can then walk
+    /// through all of them without ever needing to seek backward, which is what
+    /// `raw_batch_scan_contiguous` relies on.
+    fn ranges_are_contiguous(ranges: &[KeyRange], reverse_scan: bool) -> bool {
+        ranges.windows(2).all(|w| {
+            if reverse_scan {
+                w[0].get_start_key() > w[1].get_start_key()
+            } else {
+                w[0].get_start_key() < w[1].get_start_key()
+            }
+        })
+    }
+
+    /// Scans every range in `ranges` with a single cursor, re-seeking only when the cursor
+    /// isn't already positioned inside the next range (at or past its start going forward,
+    /// strictly before it in reverse, whose start is exclusive). Callers must first check
+    /// [`ranges_are_contiguous`](Self::ranges_are_contiguous); this avoids the per-range cursor
+    /// allocation and from-scratch seek that `forward_raw_scan`/`reverse_raw_scan` pay when
+    /// called once per range, which matters for workloads that batch-scan many small adjacent
+    /// ranges. `filter` behaves as in
+    /// [`forward_raw_scan`](Storage::forward_raw_scan), applied independently within each range.
+    fn raw_batch_scan_contiguous(
+        snapshot: &E::Snap,
+        cf: &str,
+        ranges: &[KeyRange],
+        each_limit: usize,
+        statistics: &mut Statistics,
+        key_only: bool,
+        reverse_scan: bool,
+        projection: &[Vec<u8>],
+        filter: &RawValueFilter,
+    ) -> Result<Vec<(RequestInfo, Vec<Result<KvPair>>)>> {
+        let raw_cf = Self::rawkv_cf(cf)?;
+        // Values are always read, even for a `key_only` scan, so an expired entry's TTL can
+        // still be checked and the entry skipped rather than returned.
+        let option = IterOptions::default();
+        let scan_mode = if reverse_scan {
+            ScanMode::Backward
+        } else {
+            ScanMode::Forward
+        };
+        let mut cursor = snapshot.iter_cf(raw_cf, option, scan_mode)?;
+        let cf_stats = statistics.mut_cf_statistics(cf);
+
+        let ranges_len = ranges.len();
+        let mut out = Vec::with_capacity(ranges_len);
+        let mut cursor_live = false;
+        for (i, range) in ranges.iter().enumerate() {
+            let start_key = Key::from_encoded_slice(range.get_start_key());
+            let end_key_bytes = range.get_end_key();
+            let end_key = if end_key_bytes.is_empty() {
+                if i + 1 == ranges_len {
+                    None
+                } else {
+                    Some(Key::from_encoded_slice(ranges[i + 1].get_start_key()))
+                }
+            } else {
+                Some(Key::from_encoded_slice(end_key_bytes))
+            };
+            let req_info = build_req_info(range.get_start_key(), end_key_bytes, reverse_scan);
+
+            let positioned = cursor_live
+                && cursor.valid()?
+                && if reverse_scan {
+                    cursor.key(cf_stats) < start_key.as_encoded().as_slice()
+                } else {
+                    cursor.key(cf_stats) >= start_key.as_encoded().as_slice()
+                };
+            if !positioned {
+                cursor_live = if reverse_scan {
+                    cursor.reverse_seek(&start_key, cf_stats)?
+                } else {
+                    cursor.seek(&start_key, cf_stats)?
+                };
+            }
+
+            let mut pairs = vec![];
+            while cursor_live && cursor.valid()? && pairs.len() < each_limit {
+                let key = cursor.key(cf_stats).to_owned();
+                if let Some(end) = &end_key {
+                    let past_end = if reverse_scan {
+                        key.as_slice() < end.as_encoded().as_slice()
+                    } else {
+                        key.as_slice() >= end.as_encoded().as_slice()
+                    };
+                    if past_end {
+                        break;
+                    }
+                }
+                if chunking::is_reserved_key(&key) {
+                    // Internal chunk content, not an ordinary raw row -- never counted against
+                    // `each_limit` or handed back to the caller.
+                    if reverse_scan {
+                        cursor.prev(cf_stats);
+                    } else {
+                        cursor.next(cf_stats);
+                    }
+                    continue;
+                }
+                let raw_value = cursor.value(cf_stats).to_owned();
+                match Self::decode_live_raw_value(snapshot, raw_cf, raw_value) {
+                    Ok(Some(value)) if filter.matches(&value) => {
+                        pairs.push(Self::project_raw_value(value, key_only, projection).map(|v| (key, v)))
+                    }
+                    Ok(Some(_)) | Ok(None) => {}
+                    Err(e) => pairs.push(Err(e)),
+                }
+                if reverse_scan {
+                    cursor.prev(cf_stats);
+                } else {
+                    cursor.next(cf_stats);
+                }
+            }
+            out.push((req_info, pairs));
+        }
+        Ok(out)
+    }
+
     /// Scan raw keys in a range.
     ///
     /// If `reverse_scan` is false, the range is [`start_key`, `end_key`); otherwise, the range is
@@ -1254,6 +2365,23 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     ///
     /// If `key_only` is true, the value
     /// corresponding to the key will not be read out. Only scanned keys will be returned.
+    ///
+    /// Each value is still read internally so its TTL can be checked; a `key_only` scan returns
+    /// it empty.
+    ///
+    /// If `sample_step` is non-zero, only every `sample_step`-th key visited is returned (still
+    /// counted against `limit`), for cheap range-size estimation and split-point selection
+    /// without materializing every key.
+    ///
+    /// If `projection` is non-empty and `key_only` is false, each returned value is re-encoded by
+    /// [`field_codec::project`] to just the named top-level fields (see
+    /// [`raw_get_field`](Storage::raw_get_field) for the value format this assumes); an empty
+    /// `projection` keeps today's full-value behavior.
+    ///
+    /// `filter` is evaluated against each value before it counts against `limit`; a row it
+    /// rejects never reaches `projection` and never crosses the wire.
+    /// [`RawValueFilter::None`] keeps today's behavior of accepting every row.
+    #[allow(clippy::too_many_arguments)]
     pub fn raw_scan(
         &self,
         ctx: Context,
@@ -1263,6 +2391,9 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         limit: usize,
         key_only: bool,
         reverse_scan: bool,
+        sample_step: usize,
+        projection: Vec<Vec<u8>>,
+        filter: RawValueFilter,
     ) -> impl Future<Output = Result<Vec<Result<KvPair>>>> {
         const CMD: CommandKind = CommandKind::raw_scan;
         let priority = ctx.get_priority();
@@ -1312,6 +2443,9 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                             limit,
                             &mut statistics,
                             key_only,
+                            sample_step,
+                            &projection,
+                            &filter,
                         )
                         .map_err(Error::from)
                     } else {
@@ -1323,15 +2457,24 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                             limit,
                             &mut statistics,
                             key_only,
+                            sample_step,
+                            &projection,
+                            &filter,
                         )
                         .map_err(Error::from)
                     };
+                    if let Ok(pairs) = &result {
+                        for (key, _) in pairs.iter().filter_map(|p| p.as_ref().ok()) {
+                            metrics::tls_collect_hot_key(ctx.get_region_id(), ctx.get_peer(), key);
+                        }
+                    }
                     metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &statistics);
                     metrics::tls_collect_read_flow(ctx.get_region_id(), &statistics);
                     KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                         .get(CMD)
+                        .get(priority_tag)
                         .observe(statistics.write.flow_stats.read_keys as f64);
-                    metrics::tls_collect_scan_details(CMD, &statistics);
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
                     SCHED_PROCESSING_READ_HISTOGRAM_STATIC
                         .get(CMD)
                         .observe(begin_instant.elapsed_secs());
@@ -1389,6 +2532,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     }
 
     /// Scan raw keys in multiple ranges in a batch.
+    ///
+    /// `projection` and `filter` behave as in [`raw_scan`](Storage::raw_scan), applied
+    /// independently to each range.
+    #[allow(clippy::too_many_arguments)]
     pub fn raw_batch_scan(
         &self,
         ctx: Context,
@@ -1397,6 +2544,8 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         each_limit: usize,
         key_only: bool,
         reverse_scan: bool,
+        projection: Vec<Vec<u8>>,
+        filter: RawValueFilter,
     ) -> impl Future<Output = Result<Vec<Result<KvPair>>>> {
         const CMD: CommandKind = CommandKind::raw_batch_scan;
         let priority = ctx.get_priority();
@@ -1423,54 +2572,89 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     };
                     let mut result = Vec::new();
                     let ranges_len = ranges.len();
-                    for i in 0..ranges_len {
-                        let req_info = build_req_info(
-                            &ranges[i].start_key,
-                            &ranges[i].end_key,
+
+                    if Self::ranges_are_contiguous(&ranges, reverse_scan) {
+                        let scanned = Self::raw_batch_scan_contiguous(
+                            &snapshot,
+                            &cf,
+                            &ranges,
+                            each_limit,
+                            &mut statistics,
+                            key_only,
                             reverse_scan,
-                        );
+                            &projection,
+                            &filter,
+                        )?;
+                        for (req_info, pairs) in scanned {
+                            result.extend(pairs.into_iter());
+
+                            let mut stats = Statistics::default();
+                            stats.data.flow_stats.read_keys = statistics.total_read_keys() - pre_read_keys;
+                            stats.data.flow_stats.read_bytes = statistics.total_read_bytes() - pre_read_bytes;
+                            metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &stats);
 
-                        let start_key = Key::from_encoded(ranges[i].take_start_key());
-                        let end_key = ranges[i].take_end_key();
-                        let end_key = if end_key.is_empty() {
-                            if i + 1 == ranges_len {
-                                None
+                            pre_read_keys = statistics.total_read_keys();
+                            pre_read_bytes = statistics.total_read_bytes();
+                        }
+                    } else {
+                        for i in 0..ranges_len {
+                            let req_info = build_req_info(
+                                &ranges[i].start_key,
+                                &ranges[i].end_key,
+                                reverse_scan,
+                            );
+
+                            let start_key = Key::from_encoded(ranges[i].take_start_key());
+                            let end_key = ranges[i].take_end_key();
+                            let end_key = if end_key.is_empty() {
+                                if i + 1 == ranges_len {
+                                    None
+                                } else {
+                                    Some(Key::from_encoded_slice(ranges[i + 1].get_start_key()))
+                                }
                             } else {
-                                Some(Key::from_encoded_slice(ranges[i + 1].get_start_key()))
-                            }
-                        } else {
-                            Some(Key::from_encoded(end_key))
-                        };
-                        let pairs = if reverse_scan {
-                            Self::reverse_raw_scan(
-                                &snapshot,
-                                &cf,
-                                &start_key,
-                                end_key,
-                                each_limit,
-                                &mut statistics,
-                                key_only,
-                            )?
-                        } else {
-                            Self::forward_raw_scan(
-                                &snapshot,
-                                &cf,
-                                &start_key,
-                                end_key,
-                                each_limit,
-                                &mut statistics,
-                                key_only,
-                            )?
-                        };
-                        result.extend(pairs.into_iter());
+                                Some(Key::from_encoded(end_key))
+                            };
+                            let pairs = if reverse_scan {
+                                Self::reverse_raw_scan(
+                                    &snapshot,
+                                    &cf,
+                                    &start_key,
+                                    end_key,
+                                    each_limit,
+                                    &mut statistics,
+                                    key_only,
+                                    0,
+                                    &projection,
+                                    &filter,
+                                )?
+                            } else {
+                                Self::forward_raw_scan(
+                                    &snapshot,
+                                    &cf,
+                                    &start_key,
+                                    end_key,
+                                    each_limit,
+                                    &mut statistics,
+                                    key_only,
+                                    0,
+                                    &projection,
+                                    &filter,
+                                )?
+                            };
+                            result.extend(pairs.into_iter());
 
-                        let mut stats = Statistics::default();
-                        stats.data.flow_stats.read_keys = statistics.total_read_keys() - pre_read_keys;
-                        stats.data.flow_stats.read_bytes = statistics.total_read_bytes() - pre_read_bytes;
-                        metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &stats);
+                            let mut stats = Statistics::default();
+                            stats.data.flow_stats.read_keys = statistics.total_read_keys() - pre_read_keys;
+                            stats.data.flow_stats.read_bytes = statistics.total_read_bytes() - pre_read_bytes;
+                            metrics::tls_collect_req_info(ctx.get_region_id(), ctx.get_peer(), req_info, &stats);
 
-                        pre_read_keys = statistics.total_read_keys();
-                        pre_read_bytes = statistics.total_read_bytes();
+                            pre_read_keys = statistics.total_read_keys();
+                            pre_read_bytes = statistics.total_read_bytes();
+                        }
+                    }
+                    for (key, _) in result.iter().filter_map(|p| p.as_ref().ok()) {
+                        metrics::tls_collect_hot_key(ctx.get_region_id(), ctx.get_peer(), key);
                     }
                     // let mut key_ranges = vec![];
                     // for range in ranges {
@@ -1484,8 +2668,9 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     metrics::tls_collect_read_flow(ctx.get_region_id(), &statistics);
                     KV_COMMAND_KEYREAD_HISTOGRAM_STATIC
                         .get(CMD)
+                        .get(priority_tag)
                         .observe(statistics.write.flow_stats.read_keys as f64);
-                    metrics::tls_collect_scan_details(CMD, &statistics);
+                    metrics::tls_collect_scan_details(CMD, priority_tag, &statistics);
                     SCHED_PROCESSING_READ_HISTOGRAM_STATIC
                         .get(CMD)
                         .observe(begin_instant.elapsed_secs());
@@ -1514,6 +2699,15 @@ fn get_priority_tag(priority: CommandPri) -> CommandPriority {
     }
 }
 
+// TODO: the other half of async-commit -- `commands::Prewrite` computing and persisting
+// `min_commit_ts` itself (via `cm.lock_key`/`cm.update_max_ts`, as `async_commit_check_keys`
+// below already does on the read side), the 1PC variant that commits every key of a
+// single-request transaction atomically at that `min_commit_ts` with no separate `Commit`
+// command, and `ResolveLock` pushing a live transaction's `min_commit_ts` forward instead of
+// rolling it back -- all live in `txn::commands`/`mvcc`'s prewrite and resolve-lock logic,
+// neither present in this checkout. `CheckTxnStatus`'s own push-forward (see
+// `test_check_txn_status`) and the reader-side blocking decision (`test_check_memory_locks`,
+// `test_check_memory_locks_min_commit_ts`) are the parts reachable from this file.
 fn async_commit_check_keys<'a>(
     concurrency_manager: &ConcurrencyManager,
     keys: impl IntoIterator<Item = &'a Key>,
@@ -1701,6 +2895,46 @@ pub mod test_util {
         })
     }
 
+    pub fn expect_check_txn_status_callback(
+        done: Sender<i32>,
+        id: i32,
+        status: TxnStatus,
+    ) -> Callback<TxnStatus> {
+        Box::new(move |res: Result<TxnStatus>| {
+            assert_eq!(res.unwrap(), status);
+            done.send(id).unwrap();
+        })
+    }
+
+    type TxnHeartBeatCommand = TypedCommand<TxnStatus>;
+
+    pub fn new_txn_heart_beat_command(
+        primary_key: Key,
+        start_ts: impl Into<TimeStamp>,
+        advise_ttl: u64,
+    ) -> TxnHeartBeatCommand {
+        commands::TxnHeartBeat::new(primary_key, start_ts.into(), advise_ttl, Context::default())
+    }
+
+    type CheckTxnStatusCommand = TypedCommand<TxnStatus>;
+
+    pub fn new_check_txn_status_command(
+        primary_key: Key,
+        lock_ts: impl Into<TimeStamp>,
+        caller_start_ts: impl Into<TimeStamp>,
+        current_ts: impl Into<TimeStamp>,
+        rollback_if_not_exist: bool,
+    ) -> CheckTxnStatusCommand {
+        commands::CheckTxnStatus::new(
+            primary_key,
+            lock_ts.into(),
+            caller_start_ts.into(),
+            current_ts.into(),
+            rollback_if_not_exist,
+            Context::default(),
+        )
+    }
+
     type PessimisticLockCommand = TypedCommand<Result<PessimisticLockRes>>;
 
     pub fn new_acquire_pessimistic_lock_command(
@@ -1787,7 +3021,7 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             100.into(),
-        )));
+        ).map(|(v, _)| v)));
         storage
             .sched_txn_command(
                 commands::Prewrite::with_defaults(
@@ -1806,7 +3040,7 @@ mod tests {
                 ))))) => (),
                 e => panic!("unexpected error chain: {:?}", e),
             },
-            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 101.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 101.into()).map(|(v, _)| v)),
         );
         storage
             .sched_txn_command(
@@ -1824,10 +3058,10 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             100.into(),
-        )));
+        ).map(|(v, _)| v)));
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 101.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 101.into()).map(|(v, _)| v)),
         );
     }
 
@@ -1871,7 +3105,7 @@ mod tests {
                 ))))) => (),
                 e => panic!("unexpected error chain: {:?}", e),
             },
-            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 1.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 1.into()).map(|(v, _)| v)),
         );
         expect_error(
             |e| match e {
@@ -1889,6 +3123,7 @@ mod tests {
                 1.into(),
                 false,
                 false,
+                None,
             )),
         );
         expect_error(
@@ -1902,13 +3137,15 @@ mod tests {
                 Context::default(),
                 vec![Key::from_raw(b"c"), Key::from_raw(b"d")],
                 1.into(),
-            )),
+            ))
+            .map(|(v, _)| v),
         );
         let x = block_on(storage.batch_get_command(vec![
             create_get_request(b"c", 1),
             create_get_request(b"d", 1),
         ]))
-        .unwrap();
+        .unwrap()
+        .0;
         for v in x {
             expect_error(
                 |e| match e {
@@ -1957,6 +3194,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward
@@ -1971,6 +3209,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
         // Forward with bound
@@ -1985,6 +3224,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward with bound
@@ -1999,6 +3239,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
         // Forward with limit
@@ -2013,6 +3254,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward with limit
@@ -2027,6 +3269,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
 
@@ -2062,6 +3305,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward
@@ -2080,6 +3324,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
         // Forward with sample step
@@ -2097,6 +3342,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward with sample step
@@ -2114,6 +3360,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
         // Forward with sample step and limit
@@ -2128,6 +3375,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward with sample step and limit
@@ -2142,6 +3390,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
         // Forward with bound
@@ -2159,6 +3408,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward with bound
@@ -2176,6 +3426,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
 
@@ -2194,6 +3445,7 @@ mod tests {
                 5.into(),
                 false,
                 false,
+                None,
             )),
         );
         // Backward with limit
@@ -2211,6 +3463,7 @@ mod tests {
                 5.into(),
                 false,
                 true,
+                None,
             )),
         );
     }
@@ -2269,6 +3522,7 @@ mod tests {
                 5.into(),
                 true,
                 false,
+                None,
             )),
         );
         // Backward
@@ -2283,6 +3537,7 @@ mod tests {
                 5.into(),
                 true,
                 true,
+                None,
             )),
         );
         // Forward with bound
@@ -2297,6 +3552,7 @@ mod tests {
                 5.into(),
                 true,
                 false,
+                None,
             )),
         );
         // Backward with bound
@@ -2311,6 +3567,7 @@ mod tests {
                 5.into(),
                 true,
                 true,
+                None,
             )),
         );
         // Forward with limit
@@ -2325,6 +3582,7 @@ mod tests {
                 5.into(),
                 true,
                 false,
+                None,
             )),
         );
         // Backward with limit
@@ -2339,6 +3597,7 @@ mod tests {
                 5.into(),
                 true,
                 true,
+                None,
             )),
         );
 
@@ -2374,6 +3633,7 @@ mod tests {
                 5.into(),
                 true,
                 false,
+                None,
             )),
         );
         // Backward
@@ -2392,6 +3652,7 @@ mod tests {
                 5.into(),
                 true,
                 true,
+                None,
             )),
         );
         // Forward with bound
@@ -2406,6 +3667,7 @@ mod tests {
                 5.into(),
                 true,
                 false,
+                None,
             )),
         );
         // Backward with bound
@@ -2420,6 +3682,7 @@ mod tests {
                 5.into(),
                 true,
                 true,
+                None,
             )),
         );
 
@@ -2435,6 +3698,7 @@ mod tests {
                 5.into(),
                 true,
                 false,
+                None,
             )),
         );
         // Backward with limit
@@ -2449,6 +3713,89 @@ mod tests {
                 5.into(),
                 true,
                 true,
+                None,
+            )),
+        );
+    }
+
+    #[test]
+    fn test_scan_with_value_predicate() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+        storage
+            .sched_txn_command(
+                commands::Prewrite::with_defaults(
+                    vec![
+                        Mutation::Put((Key::from_raw(b"a"), b"1".to_vec())),
+                        Mutation::Put((Key::from_raw(b"b"), b"20".to_vec())),
+                        Mutation::Put((Key::from_raw(b"c"), b"300".to_vec())),
+                    ],
+                    b"a".to_vec(),
+                    1.into(),
+                ),
+                expect_ok_callback(tx.clone(), 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        storage
+            .sched_txn_command(
+                commands::Commit::new(
+                    vec![
+                        Key::from_raw(b"a"),
+                        Key::from_raw(b"b"),
+                        Key::from_raw(b"c"),
+                    ],
+                    1.into(),
+                    2.into(),
+                    Context::default(),
+                ),
+                expect_ok_callback(tx, 1),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // Only `b` and `c` have a value >= 20.
+        expect_multi_values(
+            vec![
+                Some((b"b".to_vec(), b"20".to_vec())),
+                Some((b"c".to_vec(), b"300".to_vec())),
+            ],
+            block_on(storage.scan(
+                Context::default(),
+                Key::from_raw(b"\x00"),
+                None,
+                1000,
+                0,
+                5.into(),
+                false,
+                false,
+                Some(ScanPredicate::new(
+                    predicate::Conversion::Integer,
+                    predicate::CmpOp::Ge,
+                    predicate::TypedLiteral::Integer(20),
+                )),
+            )),
+        );
+        // `limit` counts matching keys, so it doesn't cut the scan short on `a`, which the
+        // predicate discards.
+        expect_multi_values(
+            vec![Some((b"b".to_vec(), b"20".to_vec()))],
+            block_on(storage.scan(
+                Context::default(),
+                Key::from_raw(b"\x00"),
+                None,
+                1,
+                0,
+                5.into(),
+                false,
+                false,
+                Some(ScanPredicate::new(
+                    predicate::Conversion::Integer,
+                    predicate::CmpOp::Ge,
+                    predicate::TypedLiteral::Integer(20),
+                )),
             )),
         );
     }
@@ -2480,7 +3827,8 @@ mod tests {
                 Context::default(),
                 vec![Key::from_raw(b"c"), Key::from_raw(b"d")],
                 2.into(),
-            )),
+            ))
+            .map(|(v, _)| v),
         );
         storage
             .sched_txn_command(
@@ -2513,7 +3861,8 @@ mod tests {
                     Key::from_raw(b"b"),
                 ],
                 5.into(),
-            )),
+            ))
+            .map(|(v, _)| v),
         );
     }
 
@@ -2549,7 +3898,8 @@ mod tests {
             create_get_request(b"c", 2),
             create_get_request(b"d", 2),
         ]))
-        .unwrap();
+        .unwrap()
+        .0;
         expect_error(
             |e| match e {
                 Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::Mvcc(mvcc::Error(
@@ -2583,6 +3933,7 @@ mod tests {
             create_get_request(b"b", 5),
         ]))
         .unwrap()
+        .0
         .into_iter()
         .map(|x| x.unwrap())
         .collect();
@@ -2597,6 +3948,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_batch_get_command_with_status() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+        storage
+            .sched_txn_command(
+                commands::Prewrite::with_defaults(
+                    vec![
+                        Mutation::Put((Key::from_raw(b"a"), b"aa".to_vec())),
+                        Mutation::Put((Key::from_raw(b"b"), b"bb".to_vec())),
+                    ],
+                    b"a".to_vec(),
+                    1.into(),
+                ),
+                expect_ok_callback(tx.clone(), 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // `a` and `b` are still locked by the in-flight prewrite above; `c` was never written.
+        let statuses = block_on(storage.batch_get_command_with_status(
+            vec![
+                create_get_request(b"a", 2),
+                create_get_request(b"b", 2),
+                create_get_request(b"c", 2),
+            ],
+            false,
+        ))
+        .unwrap();
+        match &statuses[0] {
+            KeyStatus::Locked { lock_ts, primary } => {
+                assert_eq!(*lock_ts, 1.into());
+                assert_eq!(primary.as_slice(), b"a");
+            }
+            other => panic!("unexpected status: {:?}", other),
+        }
+        assert!(statuses[1].is_locked());
+        assert!(matches!(statuses[2], KeyStatus::NotFound));
+
+        storage
+            .sched_txn_command(
+                commands::Commit::new(
+                    vec![Key::from_raw(b"a"), Key::from_raw(b"b")],
+                    1.into(),
+                    2.into(),
+                    Context::default(),
+                ),
+                expect_ok_callback(tx, 1),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        let statuses = block_on(storage.batch_get_command_with_status(
+            vec![
+                create_get_request(b"a", 5),
+                create_get_request(b"b", 5),
+                create_get_request(b"c", 5),
+            ],
+            false,
+        ))
+        .unwrap();
+        match &statuses[0] {
+            KeyStatus::Found(v) => assert_eq!(v, b"aa"),
+            other => panic!("unexpected status: {:?}", other),
+        }
+        match &statuses[1] {
+            KeyStatus::Found(v) => assert_eq!(v, b"bb"),
+            other => panic!("unexpected status: {:?}", other),
+        }
+        assert!(matches!(statuses[2], KeyStatus::NotFound));
+    }
+
     #[test]
     fn test_txn() {
         let storage = TestStorageBuilder::new(DummyLockManager {})
@@ -2651,11 +4076,11 @@ mod tests {
         rx.recv().unwrap();
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 120.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 120.into()).map(|(v, _)| v)),
         );
         expect_value(
             b"101".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"y"), 120.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"y"), 120.into()).map(|(v, _)| v)),
         );
         storage
             .sched_txn_command(
@@ -2688,7 +4113,7 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             100.into(),
-        )));
+        ).map(|(v, _)| v)));
         storage
             .sched_txn_command::<()>(
                 commands::Pause::new(vec![Key::from_raw(b"x")], 1000, Context::default()).into(),
@@ -2753,7 +4178,7 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             105.into(),
-        )));
+        ).map(|(v, _)| v)));
     }
 
     #[test]
@@ -2811,7 +4236,7 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             ts(230, 0),
-        )));
+        ).map(|(v, _)| v)));
     }
 
     #[test]
@@ -2822,7 +4247,7 @@ mod tests {
         let (tx, rx) = channel();
         let mut ctx = Context::default();
         ctx.set_priority(CommandPri::High);
-        expect_none(block_on(storage.get(ctx, Key::from_raw(b"x"), 100.into())));
+        expect_none(block_on(storage.get(ctx, Key::from_raw(b"x"), 100.into()).map(|(v, _)| v)));
         let mut ctx = Context::default();
         ctx.set_priority(CommandPri::High);
         storage
@@ -2848,12 +4273,12 @@ mod tests {
         rx.recv().unwrap();
         let mut ctx = Context::default();
         ctx.set_priority(CommandPri::High);
-        expect_none(block_on(storage.get(ctx, Key::from_raw(b"x"), 100.into())));
+        expect_none(block_on(storage.get(ctx, Key::from_raw(b"x"), 100.into()).map(|(v, _)| v)));
         let mut ctx = Context::default();
         ctx.set_priority(CommandPri::High);
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(ctx, Key::from_raw(b"x"), 101.into())),
+            block_on(storage.get(ctx, Key::from_raw(b"x"), 101.into()).map(|(v, _)| v)),
         );
     }
 
@@ -2870,7 +4295,7 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             100.into(),
-        )));
+        ).map(|(v, _)| v)));
         storage
             .sched_txn_command(
                 commands::Prewrite::with_defaults(
@@ -2905,7 +4330,7 @@ mod tests {
         ctx.set_priority(CommandPri::High);
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(ctx, Key::from_raw(b"x"), 101.into())),
+            block_on(storage.get(ctx, Key::from_raw(b"x"), 101.into()).map(|(v, _)| v)),
         );
         // Command Get with high priority not block by command Pause.
         assert_eq!(rx.recv().unwrap(), 3);
@@ -2951,15 +4376,15 @@ mod tests {
         rx.recv().unwrap();
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 101.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"x"), 101.into()).map(|(v, _)| v)),
         );
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"y"), 101.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"y"), 101.into()).map(|(v, _)| v)),
         );
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"z"), 101.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"z"), 101.into()).map(|(v, _)| v)),
         );
 
         // Delete range [x, z)
@@ -2977,15 +4402,15 @@ mod tests {
             Context::default(),
             Key::from_raw(b"x"),
             101.into(),
-        )));
+        ).map(|(v, _)| v)));
         expect_none(block_on(storage.get(
             Context::default(),
             Key::from_raw(b"y"),
             101.into(),
-        )));
+        ).map(|(v, _)| v)));
         expect_value(
             b"100".to_vec(),
-            block_on(storage.get(Context::default(), Key::from_raw(b"z"), 101.into())),
+            block_on(storage.get(Context::default(), Key::from_raw(b"z"), 101.into()).map(|(v, _)| v)),
         );
 
         storage
@@ -3002,7 +4427,7 @@ mod tests {
             Context::default(),
             Key::from_raw(b"z"),
             101.into(),
-        )));
+        ).map(|(v, _)| v)));
     }
 
     #[test]
@@ -3028,6 +4453,7 @@ mod tests {
                     "".to_string(),
                     kv.0.to_vec(),
                     kv.1.to_vec(),
+                    None,
                     expect_ok_callback(tx.clone(), 0),
                 )
                 .unwrap();
@@ -3132,6 +4558,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                None,
                 expect_ok_callback(tx, 0),
             )
             .unwrap();
@@ -3169,6 +4596,7 @@ mod tests {
                     "".to_string(),
                     key.clone(),
                     value.clone(),
+                    None,
                     expect_ok_callback(tx.clone(), 0),
                 )
                 .unwrap();
@@ -3184,6 +4612,157 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_raw_batch_get_encoded() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"a".to_vec(),
+                b"aa".to_vec(),
+                None,
+                expect_ok_callback(tx, 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // Unlike `raw_batch_get`, a missing key is kept as a `found: false` row instead of being
+        // dropped, so the encoded response still has one row per requested key.
+        let keys = vec![b"a".to_vec(), b"missing".to_vec()];
+        let expected = vec![
+            (b"a".to_vec(), Some(b"aa".to_vec())),
+            (b"missing".to_vec(), None),
+        ];
+
+        let raw_encoded = block_on(storage.raw_batch_get_encoded(
+            Context::default(),
+            "".to_string(),
+            keys.clone(),
+            response_codec::ResponseCodec::Raw,
+        ))
+        .unwrap();
+        assert_eq!(response_codec::decode_raw(&raw_encoded), expected);
+
+        // The `Flexbuffers` encoding carries the same rows, just in a different wire layout.
+        let flex_encoded = block_on(storage.raw_batch_get_encoded(
+            Context::default(),
+            "".to_string(),
+            keys,
+            response_codec::ResponseCodec::Flexbuffers,
+        ))
+        .unwrap();
+        let root = flexbuffers::Reader::get_root(flex_encoded.as_slice()).unwrap();
+        let root = root.as_vector();
+        assert_eq!(root.len(), expected.len());
+        for (row, (key, value)) in root.iter().zip(expected.iter()) {
+            let row = row.as_map();
+            assert_eq!(&*row.index("key").unwrap().as_blob(), key.as_slice());
+            assert_eq!(row.index("found").unwrap().as_bool(), value.is_some());
+            assert_eq!(
+                &*row.index("value").unwrap().as_blob(),
+                value.as_deref().unwrap_or(&[]),
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_get_field_and_scan_projection() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+
+        let mut value_a = flexbuffers::Builder::default();
+        {
+            let mut map = value_a.start_map();
+            map.push("name", "alice");
+            map.push("age", 30u64);
+        }
+        let value_a = value_a.view().to_vec();
+
+        let mut value_b = flexbuffers::Builder::default();
+        {
+            let mut map = value_b.start_map();
+            map.push("name", "bob");
+            map.push("age", 40u64);
+        }
+        let value_b = value_b.view().to_vec();
+
+        for (key, value) in [(b"a".to_vec(), &value_a), (b"b".to_vec(), &value_b)] {
+            storage
+                .raw_put(
+                    Context::default(),
+                    "".to_string(),
+                    key,
+                    value.clone(),
+                    None,
+                    expect_ok_callback(tx.clone(), 0),
+                )
+                .unwrap();
+        }
+        rx.recv().unwrap();
+        rx.recv().unwrap();
+
+        // `raw_get_field` walks a path down to a scalar leaf and returns it re-encoded on its own.
+        let name = block_on(storage.raw_get_field(
+            Context::default(),
+            "".to_string(),
+            b"a".to_vec(),
+            vec![b"name".to_vec()],
+        ))
+        .unwrap();
+        let name = flexbuffers::Reader::get_root(name.as_slice()).unwrap();
+        assert_eq!(name.as_str(), "alice");
+
+        // A non-empty `projection` on a scan re-encodes each value down to just the named fields.
+        let results = block_on(storage.raw_scan(
+            Context::default(),
+            "".to_string(),
+            b"".to_vec(),
+            None,
+            10,
+            false,
+            false,
+            0,
+            vec![b"name".to_vec()],
+            RawValueFilter::None,
+        ))
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        for pair in results {
+            let (_, value) = pair.unwrap();
+            let root = flexbuffers::Reader::get_root(value.as_slice()).unwrap();
+            let map = root.as_map();
+            assert!(map.index("age").is_err());
+            assert!(map.index("name").is_ok());
+        }
+
+        // An empty `projection` keeps the old full-value behavior.
+        let results = block_on(storage.raw_scan(
+            Context::default(),
+            "".to_string(),
+            b"".to_vec(),
+            None,
+            10,
+            false,
+            false,
+            0,
+            vec![],
+            RawValueFilter::None,
+        ))
+        .unwrap();
+        for pair in results {
+            let (key, value) = pair.unwrap();
+            let expected = if key == b"a" { &value_a } else { &value_b };
+            assert_eq!(&value, expected);
+        }
+    }
+
     #[test]
     fn test_batch_raw_get() {
         let storage = TestStorageBuilder::new(DummyLockManager {})
@@ -3207,6 +4786,7 @@ mod tests {
                     "".to_string(),
                     key.clone(),
                     value.clone(),
+                    None,
                     expect_ok_callback(tx.clone(), 0),
                 )
                 .unwrap();
@@ -3252,6 +4832,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                None,
                 expect_ok_callback(tx.clone(), 0),
             )
             .unwrap();
@@ -3360,6 +4941,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                None,
                 expect_ok_callback(tx, 0),
             )
             .unwrap();
@@ -3380,6 +4962,9 @@ mod tests {
                 20,
                 true,
                 false,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         results = results.split_off(10);
@@ -3393,6 +4978,9 @@ mod tests {
                 20,
                 true,
                 false,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         let mut results: Vec<Option<KvPair>> = test_data
@@ -3410,6 +4998,9 @@ mod tests {
                 20,
                 false,
                 false,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         results = results.split_off(10);
@@ -3423,6 +5014,9 @@ mod tests {
                 20,
                 false,
                 false,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         let results: Vec<Option<KvPair>> = test_data
@@ -3441,6 +5035,9 @@ mod tests {
                 20,
                 false,
                 true,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         let results: Vec<Option<KvPair>> = test_data
@@ -3460,6 +5057,9 @@ mod tests {
                 5,
                 false,
                 true,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3481,6 +5081,9 @@ mod tests {
                 20,
                 false,
                 false,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         let results: Vec<Option<KvPair>> = test_data
@@ -3500,6 +5103,9 @@ mod tests {
                 20,
                 false,
                 false,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3522,6 +5128,9 @@ mod tests {
                 20,
                 false,
                 true,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
         let results: Vec<Option<KvPair>> = test_data
@@ -3540,6 +5149,9 @@ mod tests {
                 20,
                 false,
                 true,
+                0,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3569,6 +5181,9 @@ mod tests {
                     20,
                     &mut Statistics::default(),
                     false,
+                    0,
+                    &[],
+                    &RawValueFilter::None,
                 )
             }),
         );
@@ -3585,11 +5200,79 @@ mod tests {
                     20,
                     &mut Statistics::default(),
                     false,
+                    0,
+                    &[],
+                    &RawValueFilter::None,
                 )
             }),
         );
     }
 
+    #[test]
+    fn test_raw_scan_with_filter() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+
+        let test_data = vec![
+            (b"a".to_vec(), b"aa".to_vec()),
+            (b"b".to_vec(), b"bb".to_vec()),
+            (b"b1".to_vec(), b"bb11".to_vec()),
+            (b"c".to_vec(), b"cc".to_vec()),
+        ];
+        storage
+            .raw_batch_put(
+                Context::default(),
+                "".to_string(),
+                test_data.clone(),
+                None,
+                expect_ok_callback(tx, 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // Only rows whose value starts with "bb" count against `limit` and are returned; the
+        // scan still covers the whole range.
+        let results = block_on(storage.raw_scan(
+            Context::default(),
+            "".to_string(),
+            vec![],
+            None,
+            20,
+            false,
+            false,
+            0,
+            vec![],
+            RawValueFilter::Prefix(b"bb".to_vec()),
+        ))
+        .unwrap();
+        let expected: Vec<(Vec<u8>, Vec<u8>)> = test_data
+            .iter()
+            .filter(|(_, v)| v.starts_with(b"bb"))
+            .cloned()
+            .collect();
+        let actual: Vec<(Vec<u8>, Vec<u8>)> =
+            results.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(actual, expected);
+
+        // An empty/`None` filter preserves the old behavior of returning every pair.
+        let results = block_on(storage.raw_scan(
+            Context::default(),
+            "".to_string(),
+            vec![],
+            None,
+            20,
+            false,
+            false,
+            0,
+            vec![],
+            RawValueFilter::None,
+        ))
+        .unwrap();
+        assert_eq!(results.len(), test_data.len());
+    }
+
     #[test]
     fn test_check_key_ranges() {
         fn make_ranges(ranges: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<KeyRange> {
@@ -3724,6 +5407,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                None,
                 expect_ok_callback(tx, 0),
             )
             .unwrap();
@@ -3769,6 +5453,8 @@ mod tests {
                 5,
                 false,
                 false,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3796,6 +5482,8 @@ mod tests {
                 5,
                 true,
                 false,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3819,6 +5507,8 @@ mod tests {
                 3,
                 false,
                 false,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3842,6 +5532,8 @@ mod tests {
                 3,
                 true,
                 false,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3878,6 +5570,8 @@ mod tests {
                 5,
                 false,
                 true,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3906,6 +5600,8 @@ mod tests {
                 2,
                 false,
                 true,
+                vec![],
+                RawValueFilter::None,
             )),
         );
 
@@ -3942,10 +5638,240 @@ mod tests {
                 5,
                 true,
                 true,
+                vec![],
+                RawValueFilter::None,
+            )),
+        );
+    }
+
+    #[test]
+    fn test_raw_compare_and_swap() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+
+        // CAS against a key that doesn't exist yet must be given `previous_value: None`.
+        let (observed, applied) = block_on(storage.raw_compare_and_swap(
+            Context::default(),
+            "".to_string(),
+            b"k1".to_vec(),
+            None,
+            b"v1".to_vec(),
+        ))
+        .unwrap();
+        assert_eq!((observed, applied), (None, true));
+        expect_value(
+            b"v1".to_vec(),
+            block_on(storage.raw_get(Context::default(), "".to_string(), b"k1".to_vec())),
+        );
+
+        // A stale `previous_value` must be rejected without writing, and report what's
+        // actually there.
+        let (observed, applied) = block_on(storage.raw_compare_and_swap(
+            Context::default(),
+            "".to_string(),
+            b"k1".to_vec(),
+            Some(b"stale".to_vec()),
+            b"v2".to_vec(),
+        ))
+        .unwrap();
+        assert_eq!((observed, applied), (Some(b"v1".to_vec()), false));
+        expect_value(
+            b"v1".to_vec(),
+            block_on(storage.raw_get(Context::default(), "".to_string(), b"k1".to_vec())),
+        );
+
+        // The matching `previous_value` swaps it.
+        let (observed, applied) = block_on(storage.raw_compare_and_swap(
+            Context::default(),
+            "".to_string(),
+            b"k1".to_vec(),
+            Some(b"v1".to_vec()),
+            b"v2".to_vec(),
+        ))
+        .unwrap();
+        assert_eq!((observed, applied), (Some(b"v1".to_vec()), true));
+        expect_value(
+            b"v2".to_vec(),
+            block_on(storage.raw_get(Context::default(), "".to_string(), b"k1".to_vec())),
+        );
+    }
+
+    #[test]
+    fn test_raw_batch_atomic() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"k2".to_vec(),
+                b"old2".to_vec(),
+                None,
+                expect_ok_callback(tx, 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // One stale triple in the batch must fail the whole batch -- `k1` is untouched even
+        // though its own expected value matches.
+        let (observed, applied) = block_on(storage.raw_batch_atomic(
+            Context::default(),
+            "".to_string(),
+            vec![
+                (b"k1".to_vec(), None, b"new1".to_vec()),
+                (b"k2".to_vec(), Some(b"stale".to_vec()), b"new2".to_vec()),
+            ],
+        ))
+        .unwrap();
+        assert!(!applied);
+        assert_eq!(observed, vec![None, Some(b"old2".to_vec())]);
+        expect_none(block_on(storage.raw_get(
+            Context::default(),
+            "".to_string(),
+            b"k1".to_vec(),
+        )));
+
+        // Once every triple's expected value matches, the whole batch is applied, and a
+        // `raw_batch_scan` taken afterwards sees every key's new value under one snapshot.
+        let (_, applied) = block_on(storage.raw_batch_atomic(
+            Context::default(),
+            "".to_string(),
+            vec![
+                (b"k1".to_vec(), None, b"new1".to_vec()),
+                (b"k2".to_vec(), Some(b"old2".to_vec()), b"new2".to_vec()),
+            ],
+        ))
+        .unwrap();
+        assert!(applied);
+
+        let ranges: Vec<KeyRange> = vec![b"k1".to_vec(), b"k2".to_vec()]
+            .into_iter()
+            .map(|k| {
+                let mut range = KeyRange::default();
+                range.set_start_key(k);
+                range
+            })
+            .collect();
+        expect_multi_values(
+            vec![
+                Some((b"k1".to_vec(), b"new1".to_vec())),
+                Some((b"k2".to_vec(), b"new2".to_vec())),
+            ],
+            block_on(storage.raw_batch_scan(
+                Context::default(),
+                "".to_string(),
+                ranges,
+                1,
+                false,
+                false,
+                vec![],
+                RawValueFilter::None,
             )),
         );
     }
 
+    #[test]
+    fn test_raw_put_with_ttl() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+
+        // A put without a TTL is stored exactly as before TTL existed, and never expires.
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"no_ttl".to_vec(),
+                b"v1".to_vec(),
+                None,
+                expect_ok_callback(tx.clone(), 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_value(
+            b"v1".to_vec(),
+            block_on(storage.raw_get(Context::default(), "".to_string(), b"no_ttl".to_vec())),
+        );
+        assert_eq!(
+            block_on(storage.raw_get_key_ttl(
+                Context::default(),
+                "".to_string(),
+                b"no_ttl".to_vec(),
+            ))
+            .unwrap(),
+            ttl::TtlStatus::NoExpire,
+        );
+
+        // A put with a TTL is readable until it expires, and reports the remaining lifetime.
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"with_ttl".to_vec(),
+                b"v2".to_vec(),
+                Some(100),
+                expect_ok_callback(tx.clone(), 1),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_value(
+            b"v2".to_vec(),
+            block_on(storage.raw_get(Context::default(), "".to_string(), b"with_ttl".to_vec())),
+        );
+        match block_on(storage.raw_get_key_ttl(
+            Context::default(),
+            "".to_string(),
+            b"with_ttl".to_vec(),
+        ))
+        .unwrap()
+        {
+            ttl::TtlStatus::ExpiresIn(secs) => assert!(secs > 0 && secs <= 100),
+            other => panic!("expected ExpiresIn, got {:?}", other),
+        }
+
+        // A TTL of 0 seconds expires immediately: the key reads back as absent, and
+        // `raw_get_key_ttl` can no longer distinguish it from a key that was never written.
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"expired".to_vec(),
+                b"v3".to_vec(),
+                Some(0),
+                expect_ok_callback(tx, 2),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_none(block_on(storage.raw_get(
+            Context::default(),
+            "".to_string(),
+            b"expired".to_vec(),
+        )));
+        assert_eq!(
+            block_on(storage.raw_get_key_ttl(
+                Context::default(),
+                "".to_string(),
+                b"expired".to_vec(),
+            ))
+            .unwrap(),
+            ttl::TtlStatus::NotFound,
+        );
+        assert_eq!(
+            block_on(storage.raw_get_key_ttl(
+                Context::default(),
+                "".to_string(),
+                b"absent".to_vec(),
+            ))
+            .unwrap(),
+            ttl::TtlStatus::NotFound,
+        );
+    }
+
     #[test]
     fn test_scan_lock() {
         let storage = TestStorageBuilder::new(DummyLockManager {})
@@ -4151,6 +6077,12 @@ mod tests {
         rx.recv().unwrap();
     }
 
+    // TODO: `ResolveLock`'s write phase re-seeks the LOCK/WRITE cursors for every key in a
+    // batch, even though the batch (see `RESOLVE_LOCK_BATCH_SIZE` below) is already sorted and
+    // could be satisfied by a single forward-scanning `MvccTxn`/reader, the same `ScanMode` idea
+    // `forward_raw_scan` uses for raw scans. That reader and `ResolveLock` itself live in
+    // `mvcc`/`txn::commands`, neither present in this checkout, so the scan-mode construction
+    // path and its seek-count benchmark can't be added from this file.
     #[test]
     fn test_resolve_lock() {
         use crate::storage::txn::RESOLVE_LOCK_BATCH_SIZE;
@@ -4664,6 +6596,12 @@ mod tests {
         rx.recv().unwrap();
     }
 
+    // TODO: `CheckTxnStatus` should take an expected primary key and abort with a new
+    // `mvcc::ErrorInner::PrimaryMismatch` when the lock's actual primary doesn't match, so a
+    // stale check (e.g. racing a concurrent rollback that rewrote the lock under a different
+    // primary) can't clean up or roll back the wrong transaction. That check and error variant
+    // live in `txn::commands::CheckTxnStatus::execute` and `mvcc::ErrorInner`, neither present
+    // in this checkout, so this file can only note the gap, not close it.
     #[test]
     fn test_check_secondary_locks() {
         let storage = TestStorageBuilder::new(DummyLockManager {})
@@ -4849,6 +6787,15 @@ mod tests {
         }
 
         // Put key and key2.
+        //
+        // TODO: the `bool` here is today's whole story for "was this key pessimistically
+        // locked" -- `true` for `key` (locked above), `false` for `key2` (never locked, so the
+        // prewrite must not require a lock). A three-valued `DoPessimisticCheck` /
+        // `SkipPessimisticCheck` / `DoConstraintCheck` action would add the case where a lock
+        // may have been lost and prewrite instead scans the write CF for a conflicting commit
+        // at `ts >= for_update_ts`. Both the per-mutation type and that scan belong to
+        // `commands::PrewritePessimistic`/`mvcc`'s prewrite logic in `txn::commands`, which
+        // isn't part of this checkout, so only today's two-valued flag is exercised here.
         storage
             .sched_txn_command(
                 commands::PrewritePessimistic::new(
@@ -4937,6 +6884,22 @@ mod tests {
         test_pessimistic_lock_impl(true);
     }
 
+    // TODO: `PessimisticRollback` releasing a lock iff its `ts == start_ts` and
+    // `for_update_ts <= for_update_ts`, and collecting the released hashes so the lock manager
+    // can wake blocked waiters, is `commands::PessimisticRollback::execute`'s job -- in
+    // `txn::commands`, not present in this checkout. A multi-key test asserting which locks
+    // survive a partial rollback (via `ScanLock`) belongs next to `delete_pessimistic_lock`
+    // above once that execute path exists here to exercise.
+
+    // TODO: `WaitFor`/`WakeUp` key identifying a waiter by `Key::gen_hash()` alone means two
+    // keys whose hashes collide can wake (or deadlock-report) each other -- switching to a
+    // farmhash-based `gen_hash` and carrying the raw key bytes alongside the hash so the real
+    // waiter manager can verify before resuming/reporting would need changes to `Key::gen_hash`
+    // itself (in the external `txn_types` crate, not vendored in this checkout) and to the wait
+    // table that owns `WaitFor`/`WakeUp` (in `lock_manager`, also not present here). This test
+    // proxy only forwards whatever `lock_mgr::LockManager` calls it with, so a collision test
+    // asserting "resolving key A doesn't wake a waiter on colliding key B" can't be written
+    // against it -- there's no real wait table behind it to disambiguate.
     pub enum Msg {
         WaitFor {
             start_ts: TimeStamp,
@@ -4947,6 +6910,13 @@ mod tests {
             timeout: Option<WaitTimeout>,
         },
 
+        // TODO: `WakeUp`/`LockManager::wake_up` should take a `Vec<ReleasedLock>` built from
+        // what each mutation actually released, so the scheduler can skip the call entirely
+        // when nothing was released and `has_waiter()` is false (today `validate_wake_up_msg`'s
+        // `empty_hashes` cases still send an empty-hash wake-up). That needs the MVCC mutation
+        // paths and the `LockManager` trait itself -- in `mvcc` and `lock_manager`, neither
+        // present in this checkout -- to agree on the new `ReleasedLock` contract; this proxy
+        // can only observe whatever shape those call `wake_up` with, not change it.
         WakeUp {
             lock_ts: TimeStamp,
             hashes: Vec<u64>,
@@ -5109,7 +7079,57 @@ mod tests {
         }
     }
 
+    // An uncontended pessimistic lock acquired under `pipelined_pessimistic_lock` still
+    // succeeds and never makes the caller wait, so no `Msg::WaitFor` should be sent -- same
+    // contract as the non-pipelined path in `validate_wait_for_lock_msg`, just with the lock
+    // write itself scheduled asynchronously once the in-memory conflict check passes.
+    #[test]
+    fn validate_pipelined_pessimistic_lock_no_wait_for() {
+        let (msg_tx, msg_rx) = channel();
+        let storage = TestStorageBuilder::from_engine_and_lock_mgr(
+            TestEngineBuilder::new().build().unwrap(),
+            ProxyLockMgr::new(msg_tx),
+        )
+        .set_pipelined_pessimistic_lock(true)
+        .build()
+        .unwrap();
+
+        let (tx, rx) = channel();
+        storage
+            .sched_txn_command(
+                commands::AcquirePessimisticLock::new(
+                    vec![(Key::from_raw(b"k"), false)],
+                    b"k".to_vec(),
+                    10.into(),
+                    3000,
+                    true,
+                    10.into(),
+                    Some(WaitTimeout::Millis(100)),
+                    false,
+                    11.into(),
+                    Context::default(),
+                ),
+                expect_pessimistic_lock_res_callback(tx, PessimisticLockRes::Empty),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        assert!(msg_rx.try_recv().is_err());
+
+        // TODO: asserting that the callback fires before the lock-CF write is durable, and that
+        // a failed async write falls back to a constraint check at the following `Prewrite`
+        // (rather than requiring the lock to exist), needs the new `PessimisticLockRes` variant
+        // and the `DoConstraintCheck` prewrite path described on `PrewritePessimistic` above --
+        // both in `txn::commands`, not part of this checkout.
+    }
+
     // Test whether `Storage` sends right wake-up msgs to `LockManager`
+    //
+    // TODO: each `empty_hashes = true` case below asserts that a `Msg::WakeUp` with an empty
+    // hash list still arrives -- once the `ReleasedLock` aggregation described on `Msg::WakeUp`
+    // above lands, these should instead assert that *no* message is received at all for a
+    // no-op command. That aggregation lives in the scheduler and MVCC write functions
+    // (`txn::commands`/`mvcc`), neither present in this checkout, so this test still documents
+    // today's (not yet skip-on-empty) behavior.
     #[test]
     fn validate_wake_up_msg() {
         fn assert_wake_up_msg_eq(
@@ -5457,7 +7477,7 @@ mod tests {
 
         // Test get
         let key_error = extract_key_error(
-            &block_on(storage.get(ctx.clone(), key.clone(), 100.into())).unwrap_err(),
+            &block_on(storage.get(ctx.clone(), key.clone(), 100.into()).map(|(v, _)| v)).unwrap_err(),
         );
         assert_eq!(key_error.get_locked().get_key(), b"key");
 
@@ -5479,6 +7499,7 @@ mod tests {
                 100.into(),
                 false,
                 false,
+                None,
             ))
             .unwrap_err(),
         );
@@ -5493,9 +7514,53 @@ mod tests {
         req2.set_context(ctx);
         req2.set_key(b"key".to_vec());
         req2.set_version(100);
-        let res = block_on(storage.batch_get_command(vec![req1, req2])).unwrap();
+        let res = block_on(storage.batch_get_command(vec![req1, req2]))
+            .unwrap()
+            .0;
         assert!(res[0].is_ok());
         let key_error = extract_key_error(&res[1].as_ref().unwrap_err());
         assert_eq!(key_error.get_locked().get_key(), b"key");
     }
+
+    // An async-commit lock's `min_commit_ts` is the earliest commit timestamp the transaction
+    // could still land on, so a reader whose snapshot `ts` is strictly below it can be sure the
+    // write isn't visible yet and can safely treat the key as unlocked -- only a reader at
+    // `ts >= min_commit_ts` needs to block. Same in-memory lock table as `test_check_memory_locks`,
+    // just asserting the non-blocking half of the decision too.
+    #[test]
+    fn test_check_memory_locks_min_commit_ts() {
+        let storage = TestStorageBuilder::new(DummyLockManager {})
+            .build()
+            .unwrap();
+        let cm = storage.get_concurrency_manager();
+        let key = Key::from_raw(b"key");
+        let guard = block_on(cm.lock_key(&key));
+        guard.with_lock(|lock| {
+            *lock = Some(txn_types::Lock::new(
+                LockType::Put,
+                b"key".to_vec(),
+                10.into(),
+                100,
+                Some(vec![]),
+                0.into(),
+                1,
+                20.into(),
+            ));
+        });
+
+        let mut ctx = Context::default();
+        ctx.set_isolation_level(IsolationLevel::Si);
+
+        // A snapshot taken before the lock's min_commit_ts can't observe its write either way,
+        // so the reader proceeds without blocking on it.
+        block_on(storage.get(ctx.clone(), key.clone(), 15.into()).map(|(v, _)| v))
+            .unwrap();
+
+        // A snapshot at or after min_commit_ts might observe the write, so the reader must block
+        // (surfaced here as the same `Locked` key error `test_check_memory_locks` asserts).
+        let key_error = extract_key_error(
+            &block_on(storage.get(ctx, key, 20.into()).map(|(v, _)| v)).unwrap_err(),
+        );
+        assert_eq!(key_error.get_locked().get_key(), b"key");
+    }
 }
@@ -0,0 +1,51 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! The per-key result type behind
+//! [`Storage::batch_get_command_with_status`](super::Storage::batch_get_command_with_status).
+//!
+//! [`Storage::batch_get_command`] surfaces a locked key as a `KeyIsLocked` error buried inside
+//! the same `ErrorInner::Txn(..Mvcc(..))` chain as every other failure, which a caller has to
+//! pattern-match by hand to tell "this key is locked, try again later" apart from "this request
+//! is broken" -- see `test_batch_get_command`. [`KeyStatus`] reports that distinction directly.
+
+use txn_types::TimeStamp;
+
+use crate::storage::mvcc::{Error as MvccError, ErrorInner as MvccErrorInner};
+use crate::storage::txn::{Error as TxnError, ErrorInner as TxnErrorInner};
+use crate::storage::{Error, ErrorInner, Result};
+
+/// The outcome of reading a single key as part of a
+/// [`batch_get_command_with_status`](super::Storage::batch_get_command_with_status) batch.
+#[derive(Debug)]
+pub enum KeyStatus {
+    Found(Vec<u8>),
+    NotFound,
+    /// The key was locked by another transaction. `primary` is that transaction's primary key,
+    /// the one [`batch_get_command_with_status`](super::Storage::batch_get_command_with_status)
+    /// checks the commit status of when asked to auto-resolve.
+    Locked { lock_ts: TimeStamp, primary: Vec<u8> },
+    /// An error other than a lock, which isn't safe to paper over (e.g. a region error).
+    Failed(Error),
+}
+
+impl KeyStatus {
+    pub(super) fn from_result(res: Result<Option<Vec<u8>>>) -> KeyStatus {
+        match res {
+            Ok(Some(v)) => KeyStatus::Found(v),
+            Ok(None) => KeyStatus::NotFound,
+            Err(Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::Mvcc(MvccError(
+                box MvccErrorInner::KeyIsLocked(info),
+            )))))) => KeyStatus::Locked {
+                lock_ts: info.get_lock_version().into(),
+                primary: info.get_primary_lock().to_vec(),
+            },
+            Err(e) => KeyStatus::Failed(e),
+        }
+    }
+
+    /// `true` whenever the key was reported [`KeyStatus::Locked`], whether or not
+    /// [`batch_get_command_with_status`]'s auto-resolve pass ran for it.
+    pub fn is_locked(&self) -> bool {
+        matches!(self, KeyStatus::Locked { .. })
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Server-side value predicates for [`Storage::scan`](super::Storage::scan).
+//!
+//! Ordinary scans ship every committed value in the key range back to the caller, who then
+//! filters client-side. A [`ScanPredicate`] lets the scanner itself decode each value under a
+//! requested [`Conversion`] and drop it before it counts against `limit`, so a selective range
+//! read only pays egress for the rows that actually match.
+//!
+//! A value that doesn't parse under the requested `Conversion` is treated as non-matching
+//! rather than failing the scan; one malformed row shouldn't take down an otherwise-valid range
+//! read.
+
+use chrono::NaiveDateTime;
+
+/// How a scanned value's raw bytes should be decoded before being compared against a
+/// predicate's literal.
+#[derive(Clone, Debug)]
+pub enum Conversion {
+    /// Compare the raw bytes directly.
+    Bytes,
+    /// Parse as a base-10 ASCII integer, falling back to 8-byte little-endian.
+    Integer,
+    /// Parse as a base-10 ASCII float, falling back to 8-byte little-endian.
+    Float,
+    /// `"true"`/`"false"` (case-insensitive) or a single `0`/`1` byte.
+    Boolean,
+    /// An epoch integer, decoded the same way as [`Conversion::Integer`].
+    Timestamp,
+    /// An epoch integer parsed out of a `chrono` strftime-formatted string.
+    TimestampFmt(String),
+}
+
+/// Comparison applied between a decoded value and a predicate's literal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn eval(self, ordering: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match (self, ordering) {
+            (CmpOp::Eq, Equal) => true,
+            (CmpOp::Ne, Equal) => false,
+            (CmpOp::Ne, _) => true,
+            (CmpOp::Lt, Less) => true,
+            (CmpOp::Le, Less) | (CmpOp::Le, Equal) => true,
+            (CmpOp::Gt, Greater) => true,
+            (CmpOp::Ge, Greater) | (CmpOp::Ge, Equal) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The typed literal a predicate compares decoded values against. Variants line up with
+/// [`Conversion`]; `Timestamp` is shared by both `Conversion::Timestamp` and
+/// `Conversion::TimestampFmt`.
+#[derive(Clone, Debug)]
+pub enum TypedLiteral {
+    Bytes(Vec<u8>),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Timestamp(i64),
+}
+
+/// A server-side filter [`Storage::scan`](super::Storage::scan) applies to each committed value
+/// before counting it against `limit`.
+#[derive(Clone, Debug)]
+pub struct ScanPredicate {
+    conversion: Conversion,
+    op: CmpOp,
+    literal: TypedLiteral,
+}
+
+impl ScanPredicate {
+    pub fn new(conversion: Conversion, op: CmpOp, literal: TypedLiteral) -> Self {
+        ScanPredicate {
+            conversion,
+            op,
+            literal,
+        }
+    }
+
+    /// Decodes `value` per `self.conversion` and evaluates `self.op` against `self.literal`.
+    /// Returns `false`, not an error, when `value` doesn't parse under `self.conversion` or the
+    /// literal's type doesn't match the conversion.
+    pub fn matches(&self, value: &[u8]) -> bool {
+        match (&self.conversion, &self.literal) {
+            (Conversion::Bytes, TypedLiteral::Bytes(lit)) => {
+                self.op.eval(value.cmp(lit.as_slice()))
+            }
+            (Conversion::Integer, TypedLiteral::Integer(lit)) => decode_integer(value)
+                .map(|v| self.op.eval(v.cmp(lit)))
+                .unwrap_or(false),
+            (Conversion::Float, TypedLiteral::Float(lit)) => decode_float(value)
+                .and_then(|v| v.partial_cmp(lit))
+                .map(|o| self.op.eval(o))
+                .unwrap_or(false),
+            (Conversion::Boolean, TypedLiteral::Boolean(lit)) => decode_boolean(value)
+                .map(|v| self.op.eval(v.cmp(lit)))
+                .unwrap_or(false),
+            (Conversion::Timestamp, TypedLiteral::Timestamp(lit)) => decode_integer(value)
+                .map(|v| self.op.eval(v.cmp(lit)))
+                .unwrap_or(false),
+            (Conversion::TimestampFmt(fmt), TypedLiteral::Timestamp(lit)) => {
+                decode_timestamp_fmt(value, fmt)
+                    .map(|v| self.op.eval(v.cmp(lit)))
+                    .unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn decode_integer(value: &[u8]) -> Option<i64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|s| s.trim().parse::<i64>().ok())
+        .or_else(|| {
+            let bytes: [u8; 8] = value.try_into().ok()?;
+            Some(i64::from_le_bytes(bytes))
+        })
+}
+
+fn decode_float(value: &[u8]) -> Option<f64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .or_else(|| {
+            let bytes: [u8; 8] = value.try_into().ok()?;
+            Some(f64::from_le_bytes(bytes))
+        })
+}
+
+fn decode_boolean(value: &[u8]) -> Option<bool> {
+    match value {
+        [1] => Some(true),
+        [0] => Some(false),
+        v if v.eq_ignore_ascii_case(b"true") => Some(true),
+        v if v.eq_ignore_ascii_case(b"false") => Some(false),
+        _ => None,
+    }
+}
+
+fn decode_timestamp_fmt(value: &[u8], fmt: &str) -> Option<i64> {
+    let s = std::str::from_utf8(value).ok()?;
+    NaiveDateTime::parse_from_str(s, fmt)
+        .ok()
+        .map(|dt| dt.timestamp())
+}
@@ -0,0 +1,53 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Server-side value filters for the raw scans (`forward_raw_scan`/`reverse_raw_scan`/
+//! `raw_batch_scan_contiguous`), so a selective range read doesn't have to ship every value in
+//! range back to the caller only for it to be dropped client-side.
+//!
+//! Unlike [`predicate::ScanPredicate`](super::predicate::ScanPredicate), which filters a
+//! committed MVCC value under one of a fixed set of type conversions, [`RawValueFilter`] filters
+//! an opaque raw KV value: a byte prefix, a length bound, or (building on [`field_codec`]'s
+//! structured values) a named top-level field's encoded bytes. [`RawValueFilter::All`] combines
+//! several of these with AND; [`RawValueFilter::None`] matches everything, which is exactly
+//! today's pre-filter scan behavior.
+//!
+//! A filter is evaluated against the already TTL/chunk-resolved value, before it's counted
+//! against a scan's `limit` or handed to `projection` -- a row the filter rejects never crosses
+//! the wire and never occupies a `limit` slot.
+
+use super::field_codec;
+
+/// A server-side filter applied to each scanned raw value before it's accepted into the result.
+#[derive(Clone, Debug)]
+pub enum RawValueFilter {
+    /// Matches every value. The default; preserves the scan's pre-filter behavior exactly.
+    None,
+    /// Matches values starting with the given byte prefix.
+    Prefix(Vec<u8>),
+    /// Matches values whose byte length is at least `min` and, if `max` is `Some`, at most `max`.
+    LenRange { min: usize, max: Option<usize> },
+    /// Matches values that are a valid [`field_codec`] structured value whose top-level field at
+    /// `path` is present and its [`field_codec::get_field`]-encoded bytes equal `expected`
+    /// (itself expected to be the same encoding -- typically built with the same
+    /// `flexbuffers::Builder::build_singleton` call `get_field` ends with).
+    FieldEq { path: Vec<Vec<u8>>, expected: Vec<u8> },
+    /// Matches only when every filter in the list matches (logical AND); an empty list matches
+    /// everything, same as [`RawValueFilter::None`].
+    All(Vec<RawValueFilter>),
+}
+
+impl RawValueFilter {
+    pub fn matches(&self, value: &[u8]) -> bool {
+        match self {
+            RawValueFilter::None => true,
+            RawValueFilter::Prefix(prefix) => value.starts_with(prefix.as_slice()),
+            RawValueFilter::LenRange { min, max } => {
+                value.len() >= *min && max.map_or(true, |max| value.len() <= max)
+            }
+            RawValueFilter::FieldEq { path, expected } => field_codec::get_field(value, path)
+                .map(|field| field == *expected)
+                .unwrap_or(false),
+            RawValueFilter::All(filters) => filters.iter().all(|f| f.matches(value)),
+        }
+    }
+}
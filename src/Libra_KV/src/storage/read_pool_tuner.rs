@@ -0,0 +1,148 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Adaptive sizing hints for the storage read pool.
+//!
+//! Every read worker thread folds the [`Statistics`] of each request it serves into a
+//! rolling, thread-local [`StatisticsSummary`]. A background controller thread (spawned
+//! alongside `write-info-push`, see [`Storage::from_engine`](super::Storage::from_engine))
+//! periodically drains the summaries submitted by worker threads and recommends a read-pool
+//! worker count within `[min_thread_count, max_thread_count]`, favoring more workers when
+//! requests are observed to skip many versions per key read (a proxy for costly block reads)
+//! and fewer when requests are cheap.
+//!
+//! A summary is submitted only by [`tls_flush_read_pool_stats`], which no worker thread in this
+//! tree calls; the controller thread runs, but finds nothing to drain.
+
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+use crate::storage::kv::Statistics;
+
+/// Rolling per-thread tally of read cost, folded in by `get`/`batch_get`/`batch_get_command`
+/// once a request completes.
+#[derive(Default, Clone, Debug)]
+pub struct StatisticsSummary {
+    pub request_count: usize,
+    pub total_keys: usize,
+    pub total_bytes: usize,
+    pub processed_versions: usize,
+    pub skipped_versions: usize,
+}
+
+impl StatisticsSummary {
+    fn add(&mut self, stats: &Statistics) {
+        self.request_count += 1;
+        self.total_keys += stats.total_read_keys();
+        self.total_bytes += stats.total_read_bytes();
+        for (_, cf_stats) in stats.details_enum().iter() {
+            self.processed_versions +=
+                cf_stats.get as usize + cf_stats.next as usize + cf_stats.prev as usize;
+            self.skipped_versions += cf_stats.next_tombstone
+                + cf_stats.prev_tombstone
+                + cf_stats.seek_tombstone
+                + cf_stats.seek_for_prev_tombstone;
+        }
+    }
+
+    fn merge(&mut self, other: &StatisticsSummary) {
+        self.request_count += other.request_count;
+        self.total_keys += other.total_keys;
+        self.total_bytes += other.total_bytes;
+        self.processed_versions += other.processed_versions;
+        self.skipped_versions += other.skipped_versions;
+    }
+
+    /// Versions skipped per version actually processed; a cheap proxy for how much wasted
+    /// block-read work the average request is doing.
+    fn skip_ratio(&self) -> f64 {
+        if self.processed_versions == 0 {
+            0.0
+        } else {
+            self.skipped_versions as f64 / self.processed_versions as f64
+        }
+    }
+}
+
+thread_local! {
+    static TLS_SUMMARY: RefCell<StatisticsSummary> = RefCell::new(StatisticsSummary::default());
+}
+
+/// Folds a completed request's statistics into this thread's rolling summary.
+pub fn tls_collect_read_pool_stats(stats: &Statistics) {
+    TLS_SUMMARY.with(|s| s.borrow_mut().add(stats));
+}
+
+lazy_static! {
+    /// Summaries handed off by worker threads since the controller last ticked.
+    static ref PENDING_SUMMARIES: Mutex<Vec<StatisticsSummary>> = Mutex::new(Vec::new());
+}
+
+/// Hands this thread's rolling summary to the controller and resets it, so the next interval
+/// starts from zero. Nothing in this tree calls it, so no summary reaches
+/// `PENDING_SUMMARIES` and [`ReadPoolTuner::tick`] returns `None` on every tick.
+pub fn tls_flush_read_pool_stats() {
+    TLS_SUMMARY.with(|s| {
+        let summary = std::mem::take(&mut *s.borrow_mut());
+        if summary.request_count > 0 {
+            PENDING_SUMMARIES.lock().unwrap().push(summary);
+        }
+    });
+}
+
+/// Recommends a read-pool worker count from the summaries submitted since the last tick,
+/// growing the pool when requests skip many versions per key read and shrinking it when
+/// requests are cheap. Bounded to `[min_thread_count, max_thread_count]`, which default to
+/// half and four times the configured `scheduler_worker_pool_size` respectively, since this
+/// tree has no separate read-pool sizing config.
+pub struct ReadPoolTuner {
+    min_thread_count: usize,
+    max_thread_count: usize,
+    current_thread_count: usize,
+}
+
+impl ReadPoolTuner {
+    pub fn new(configured_thread_count: usize) -> Self {
+        let configured_thread_count = configured_thread_count.max(1);
+        ReadPoolTuner {
+            min_thread_count: (configured_thread_count / 2).max(1),
+            max_thread_count: configured_thread_count * 4,
+            current_thread_count: configured_thread_count,
+        }
+    }
+
+    /// Drains the pending summaries and returns the recommended worker count if it differs
+    /// from the last recommendation. Returns `None` when there were no completed requests to
+    /// learn from, or when the recommendation is unchanged.
+    pub fn tick(&mut self) -> Option<usize> {
+        let summaries: Vec<_> = {
+            let mut pending = PENDING_SUMMARIES.lock().unwrap();
+            pending.drain(..).collect()
+        };
+        if summaries.is_empty() {
+            return None;
+        }
+
+        let mut merged = StatisticsSummary::default();
+        for summary in &summaries {
+            merged.merge(summary);
+        }
+
+        let target = if merged.skip_ratio() > 0.5 {
+            self.current_thread_count + 1
+        } else if merged.skip_ratio() < 0.1 {
+            self.current_thread_count.saturating_sub(1)
+        } else {
+            self.current_thread_count
+        };
+        let target = target
+            .max(self.min_thread_count)
+            .min(self.max_thread_count);
+
+        if target == self.current_thread_count {
+            None
+        } else {
+            self.current_thread_count = target;
+            Some(target)
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A blocking `Storage` facade that retries the handful of errors a client can recover from on
+//! its own, instead of every caller hand-rolling a `(tx, rx)` channel and a retry loop around
+//! `block_on` -- see `test_raw_batch_scan` and friends for what that looks like today.
+//!
+//! [`SyncStorage`] wraps [`Storage`]'s raw read methods (`raw_get`, `raw_scan`, `raw_batch_scan`,
+//! `raw_batch_get`) and offers [`send_and_confirm`](SyncStorage::send_and_confirm) for txn
+//! commands. Each blocks on the underlying async call, and if the result is a
+//! `NotLeader`/`StaleEpoch`/region-not-found class error (see `extract_region_error`), asks its
+//! [`ContextResolver`] for a fresher `Context` and retries, backing off exponentially between
+//! attempts -- the same confirm-and-retry shape
+//! [`TxnClient::commit_mutations`](super::txn_client::TxnClient::commit_mutations) uses for write
+//! conflicts, just keyed on region errors instead of MVCC ones. Retries are capped by
+//! [`RetryConfig::max_attempts`]; once exhausted, or on any other kind of error, the error is
+//! returned immediately.
+//!
+//! This tree doesn't have its own region cache or PD-backed leader lookup yet, so "how to get a
+//! fresher `Context`" is left to the caller via [`ContextResolver`] rather than wired to a
+//! specific client, the same extension-point style `CoprocessorPlugin` uses for raw request
+//! handling.
+
+use std::thread;
+use std::time::Duration;
+
+use futures03::channel::oneshot;
+use futures03::executor::block_on;
+use kvproto::errorpb;
+use kvproto::kvrpcpb::{Context, KeyRange};
+use txn_types::KvPair;
+
+use crate::storage::kv::{Engine, Error as EngineError, ErrorInner as EngineErrorInner};
+use crate::storage::lock_manager::LockManager;
+use crate::storage::mvcc::{Error as MvccError, ErrorInner as MvccErrorInner};
+use crate::storage::raw_filter::RawValueFilter;
+use crate::storage::txn::{commands::TypedCommand, Error as TxnError, ErrorInner as TxnErrorInner};
+use crate::storage::types::StorageCallbackType;
+use crate::storage::{Error, ErrorInner, Result, Storage};
+
+/// How many attempts [`SyncStorage`] makes (including the first) and how it spaces them out.
+/// Attempt `n` (0-indexed, after the first failure) waits `min(base_delay * 2^n, max_delay)`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryConfig {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let scaled = self.base_delay.saturating_mul(1u32 << attempt.min(16));
+        scaled.min(self.max_delay)
+    }
+}
+
+/// Produces a fresher `Context` to retry with after `region_error` was returned for `ctx`.
+/// [`SyncStorage`] doesn't know how a `Context`'s leader/epoch should be refreshed -- that's
+/// whatever region cache or PD client the caller already has -- so it asks this trait instead.
+pub trait ContextResolver: Send + Sync {
+    fn resolve(&self, ctx: &Context, region_error: &errorpb::Error) -> Context;
+}
+
+/// `true` for exactly the region errors worth retrying with a fresh `Context`: the leader moved,
+/// the caller's epoch is stale, or the region isn't known here at all. Anything else (e.g. a key
+/// too large, or the scheduler being too busy) is returned to the caller immediately.
+fn is_retryable_region_error(e: &errorpb::Error) -> bool {
+    e.has_not_leader() || e.has_stale_epoch() || e.has_region_not_found()
+}
+
+/// Pulls the region error out of `err`, if it has one, regardless of whether it came through the
+/// raw path (`ErrorInner::Txn(.. Engine ..)`) or the MVCC path (`ErrorInner::Txn(.. Mvcc(Engine)
+/// ..)`) -- see `is_transient_conflict` in `txn_client.rs` for the same kind of nested-error
+/// matching.
+fn extract_region_error(err: &Error) -> Option<&errorpb::Error> {
+    match err {
+        Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::Engine(EngineError(
+            box EngineErrorInner::Request(e),
+        ))))) => Some(e),
+        Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::Mvcc(MvccError(
+            box MvccErrorInner::Engine(EngineError(box EngineErrorInner::Request(e))),
+        ))))) => Some(e),
+        _ => None,
+    }
+}
+
+/// A blocking [`Storage`] facade that retries `NotLeader`/`StaleEpoch`/region-not-found errors
+/// against a [`ContextResolver`]-refreshed `Context`; see the module docs for the full shape.
+pub struct SyncStorage<E: Engine, L: LockManager, R: ContextResolver> {
+    storage: Storage<E, L>,
+    resolver: R,
+    retry: RetryConfig,
+}
+
+impl<E: Engine, L: LockManager, R: ContextResolver> SyncStorage<E, L, R> {
+    pub fn new(storage: Storage<E, L>, resolver: R) -> Self {
+        SyncStorage {
+            storage,
+            resolver,
+            retry: RetryConfig::default(),
+        }
+    }
+
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn with_retries<T>(&self, mut ctx: Context, mut call: impl FnMut(Context) -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match call(ctx.clone()) {
+                Ok(v) => return Ok(v),
+                Err(e) => {
+                    let region_error = extract_region_error(&e)
+                        .filter(|re| is_retryable_region_error(re))
+                        .cloned();
+                    match region_error {
+                        Some(region_error) if attempt + 1 < self.retry.max_attempts => {
+                            ctx = self.resolver.resolve(&ctx, &region_error);
+                            thread::sleep(self.retry.backoff(attempt));
+                            attempt += 1;
+                        }
+                        _ => return Err(e),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn raw_get(&self, ctx: Context, cf: String, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        self.with_retries(ctx, |ctx| {
+            block_on(self.storage.raw_get(ctx, cf.clone(), key.clone()))
+        })
+    }
+
+    pub fn raw_batch_get(&self, ctx: Context, cf: String, keys: Vec<Vec<u8>>) -> Result<Vec<Result<KvPair>>> {
+        self.with_retries(ctx, |ctx| {
+            block_on(self.storage.raw_batch_get(ctx, cf.clone(), keys.clone()))
+        })
+    }
+
+    /// `projection` and `filter` behave as in [`Storage::raw_scan`]; `reverse_scan` selects
+    /// forward vs. reverse, same as the underlying call.
+    #[allow(clippy::too_many_arguments)]
+    pub fn raw_scan(
+        &self,
+        ctx: Context,
+        cf: String,
+        start_key: Vec<u8>,
+        end_key: Option<Vec<u8>>,
+        limit: usize,
+        key_only: bool,
+        reverse_scan: bool,
+        sample_step: usize,
+        projection: Vec<Vec<u8>>,
+        filter: RawValueFilter,
+    ) -> Result<Vec<Result<KvPair>>> {
+        self.with_retries(ctx, |ctx| {
+            block_on(self.storage.raw_scan(
+                ctx,
+                cf.clone(),
+                start_key.clone(),
+                end_key.clone(),
+                limit,
+                key_only,
+                reverse_scan,
+                sample_step,
+                projection.clone(),
+                filter.clone(),
+            ))
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn raw_batch_scan(
+        &self,
+        ctx: Context,
+        cf: String,
+        ranges: Vec<KeyRange>,
+        each_limit: usize,
+        key_only: bool,
+        reverse_scan: bool,
+        projection: Vec<Vec<u8>>,
+        filter: RawValueFilter,
+    ) -> Result<Vec<Result<KvPair>>> {
+        self.with_retries(ctx, |ctx| {
+            block_on(self.storage.raw_batch_scan(
+                ctx,
+                cf.clone(),
+                ranges.clone(),
+                each_limit,
+                key_only,
+                reverse_scan,
+                projection.clone(),
+                filter.clone(),
+            ))
+        })
+    }
+
+    /// Submits a txn command built fresh for each attempt by `build` (so a retry can carry a
+    /// refreshed `Context` down into it), waits for its callback result, and retries the whole
+    /// submission -- not just the wait -- on a retryable region error.
+    pub fn send_and_confirm<T: StorageCallbackType>(
+        &self,
+        ctx: Context,
+        build: impl Fn(Context) -> TypedCommand<T>,
+    ) -> Result<T> {
+        self.with_retries(ctx, |ctx| {
+            let (tx, rx) = oneshot::channel();
+            self.storage.sched_txn_command(
+                build(ctx),
+                Box::new(move |res| {
+                    let _ = tx.send(res);
+                }),
+            )?;
+            block_on(rx).map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+        })
+    }
+}
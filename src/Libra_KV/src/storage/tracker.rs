@@ -0,0 +1,143 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A lightweight per-request tracing facility.
+//!
+//! Every read request allocates a [`Tracker`] in [`GLOBAL_TRACKERS`] and stashes its
+//! [`TrackerToken`] in a thread-local for the duration of read-pool execution. Low level
+//! RocksDB perf-context counters and scheduling/snapshot durations are accumulated on the
+//! active tracker so that, once the request finishes, they can be surfaced to the caller
+//! as `ScanDetailV2`/`WriteDetail` and the slot can be freed.
+
+use std::cell::RefCell;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use kvproto::kvrpcpb::{ScanDetailV2, WriteDetail};
+use slab::Slab;
+
+use crate::storage::kv::Statistics;
+
+/// A cheap handle into [`GLOBAL_TRACKERS`]. Safe to copy and pass across futures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackerToken(u64);
+
+impl TrackerToken {
+    const INVALID: TrackerToken = TrackerToken(u64::MAX);
+}
+
+impl Default for TrackerToken {
+    fn default() -> Self {
+        Self::INVALID
+    }
+}
+
+/// Per-request perf-context accumulator.
+#[derive(Default, Debug, Clone)]
+pub struct Tracker {
+    pub processed_keys: usize,
+    pub total_versions: usize,
+    pub rocksdb_delete_skipped_count: usize,
+    pub rocksdb_key_skipped_count: usize,
+    pub rocksdb_block_cache_hit_count: usize,
+    pub rocksdb_block_cache_miss_count: usize,
+    pub rocksdb_block_read_byte: usize,
+    pub scheduler_wait_duration: Duration,
+    pub snapshot_duration: Duration,
+    pub process_duration: Duration,
+}
+
+impl Tracker {
+    /// Merges a `CfStatistics`-derived `Statistics` snapshot into the tracker's counters.
+    pub fn merge_statistics(&mut self, stats: &Statistics) {
+        self.processed_keys += stats.total_read_keys();
+        for (_, cf_stats) in stats.details_enum().iter() {
+            self.total_versions += cf_stats.get as usize + cf_stats.next as usize;
+            self.rocksdb_delete_skipped_count += cf_stats.next_tombstone + cf_stats.prev_tombstone;
+            self.rocksdb_key_skipped_count += cf_stats.over_seek_bound;
+        }
+    }
+
+    pub fn observe_scheduler_wait(&mut self, d: Duration) {
+        self.scheduler_wait_duration += d;
+    }
+
+    pub fn observe_snapshot(&mut self, d: Duration) {
+        self.snapshot_duration += d;
+    }
+
+    pub fn observe_process(&mut self, d: Duration) {
+        self.process_duration += d;
+    }
+
+    /// Builds the read-path exec detail for this tracker.
+    pub fn to_scan_detail_v2(&self) -> ScanDetailV2 {
+        let mut detail = ScanDetailV2::default();
+        detail.set_processed_versions(self.processed_keys as u64);
+        detail.set_total_versions(self.total_versions as u64);
+        detail.set_rocksdb_delete_skipped_count(self.rocksdb_delete_skipped_count as u64);
+        detail.set_rocksdb_key_skipped_count(self.rocksdb_key_skipped_count as u64);
+        detail.set_rocksdb_block_cache_hit_count(self.rocksdb_block_cache_hit_count as u64);
+        detail.set_rocksdb_block_read_count(self.rocksdb_block_cache_miss_count as u64);
+        detail.set_rocksdb_block_read_byte(self.rocksdb_block_read_byte as u64);
+        detail
+    }
+
+    /// Builds the write-path exec detail for this tracker.
+    pub fn to_write_detail(&self) -> WriteDetail {
+        let mut detail = WriteDetail::default();
+        detail.set_latch_wait_nanos(self.scheduler_wait_duration.as_nanos() as u64);
+        detail.set_process_nanos(self.process_duration.as_nanos() as u64);
+        detail.set_store_batch_wait_nanos(self.snapshot_duration.as_nanos() as u64);
+        detail
+    }
+}
+
+lazy_static! {
+    /// The global slab of in-flight trackers, indexed by `TrackerToken`.
+    pub static ref GLOBAL_TRACKERS: Mutex<Slab<Tracker>> = Mutex::new(Slab::new());
+}
+
+thread_local! {
+    static TLS_TRACKER_TOKEN: RefCell<TrackerToken> = RefCell::new(TrackerToken::default());
+}
+
+/// Returns the token bound to the current thread, or an invalid token if none is bound. It
+/// allocates nothing; a token from [`set_tls_tracker_token`] must be released with
+/// [`remove_tls_tracker`] once the request completes.
+pub fn get_tls_tracker_token() -> TrackerToken {
+    TLS_TRACKER_TOKEN.with(|t| *t.borrow())
+}
+
+/// Allocates a tracker in the global slab and binds it to the current thread for the
+/// duration of the enclosing async task.
+pub fn set_tls_tracker_token() -> TrackerToken {
+    let token = TrackerToken(GLOBAL_TRACKERS.lock().unwrap().insert(Tracker::default()) as u64);
+    TLS_TRACKER_TOKEN.with(|t| *t.borrow_mut() = token);
+    token
+}
+
+/// Runs `f` with mutable access to the tracker bound to the current thread, if any.
+pub fn with_tls_tracker<F: FnOnce(&mut Tracker)>(f: F) {
+    let token = get_tls_tracker_token();
+    if token == TrackerToken::INVALID {
+        return;
+    }
+    if let Some(tracker) = GLOBAL_TRACKERS.lock().unwrap().get_mut(token.0 as usize) {
+        f(tracker);
+    }
+}
+
+/// Removes the tracker bound to the current thread from the global slab, returning its
+/// final state, and clears the thread-local binding.
+pub fn remove_tls_tracker() -> Option<Tracker> {
+    let token = TLS_TRACKER_TOKEN.with(|t| t.replace(TrackerToken::default()));
+    if token == TrackerToken::INVALID {
+        return None;
+    }
+    let mut trackers = GLOBAL_TRACKERS.lock().unwrap();
+    if trackers.contains(token.0 as usize) {
+        Some(trackers.remove(token.0 as usize))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! TTL encoding for raw KV values.
+//!
+//! A value written with a TTL is prefixed with [`TTL_MAGIC`] and an 8-byte big-endian Unix
+//! expiration timestamp, the same "marker in front" shape `chunking::ChunkManifest` uses to mark
+//! its own encoded values -- including that module's fix for the same problem: [`TTL_MAGIC`] is a
+//! full 8-byte sequence rather than a single tag byte, and [`decode`] additionally verifies a
+//! checksum of the expiration timestamp before accepting the wrapper, so a value written without
+//! a TTL can't be mistaken for one unless it matches both the marker and the checksum -- that
+//! takes deliberate construction, not 9 arbitrary bytes lining up by chance.
+//!
+//! [`Storage::raw_get`](super::Storage::raw_get), [`Storage::raw_batch_get_cf`] and the raw scan
+//! methods all decode this wrapper before returning a value, treating a past expiration as "key
+//! not found". Physically removing expired entries is left to the background `raw-ttl-gc`
+//! thread started in [`Storage::from_engine`](super::Storage::from_engine); reads never need to
+//! wait on it since they already hide expired entries on their own.
+
+use std::convert::TryInto;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 8-byte marker in front of every TTL-wrapped value, long enough that an ordinary
+/// without-a-TTL value starting with these exact bytes by chance is negligible -- [`decode`]
+/// also checks a checksum of the expiration timestamp that follows, so even that chance
+/// collision isn't enough to be misread as a TTL wrapper.
+const TTL_MAGIC: [u8; 8] = *b"\xe7TiKVTTL";
+
+/// Length of the fixed TTL header: [`TTL_MAGIC`] (8 bytes) + a checksum of `expire_at` (8 bytes)
+/// + `expire_at` itself (8 bytes).
+const TTL_HEADER_LEN: usize = 24;
+
+/// Same FNV-1a construction `chunking::content_hash` uses, reused here so the TTL wrapper and
+/// the chunk manifest wrapper both get collision resistance from checksums over their own
+/// content, without either depending on the other's module.
+fn checksum(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = FNV_OFFSET;
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// The outcome of a [`Storage::raw_get_key_ttl`](super::Storage::raw_get_key_ttl) query.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TtlStatus {
+    /// The key doesn't exist, or its TTL has already passed.
+    NotFound,
+    /// The key exists and was written without a TTL, so it never expires.
+    NoExpire,
+    /// The key exists and expires in this many seconds.
+    ExpiresIn(u64),
+}
+
+/// Seconds since the Unix epoch, per the local clock. TTL expiration is a wall-clock notion
+/// throughout this module, same as `lock_ttl` on the transactional side.
+pub fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Prefixes `value` with a `ttl_secs`-from-now expiration timestamp.
+pub fn encode(value: Vec<u8>, ttl_secs: u64) -> Vec<u8> {
+    let expire_at = now_unix().saturating_add(ttl_secs);
+    let expire_at_bytes = expire_at.to_be_bytes();
+    let check = checksum(&expire_at_bytes);
+
+    let mut buf = Vec::with_capacity(TTL_HEADER_LEN + value.len());
+    buf.extend_from_slice(&TTL_MAGIC);
+    buf.extend_from_slice(&check.to_le_bytes());
+    buf.extend_from_slice(&expire_at_bytes);
+    buf.extend_from_slice(&value);
+    buf
+}
+
+/// Strips a TTL wrapper off `value` if present. Returns the unwrapped value and, if it carried
+/// one, its expiration timestamp; a value this module never wrapped is returned unchanged with
+/// `None`. Requires both the leading [`TTL_MAGIC`] bytes and a matching checksum of the
+/// expiration timestamp that follows, so an ordinary value can't be misread as TTL-tagged by
+/// accident.
+pub fn decode(value: Vec<u8>) -> (Vec<u8>, Option<u64>) {
+    if value.len() >= TTL_HEADER_LEN && value[..8] == TTL_MAGIC[..] {
+        let check = u64::from_le_bytes(value[8..16].try_into().unwrap());
+        let expire_at_bytes = &value[16..24];
+        if checksum(expire_at_bytes) == check {
+            let expire_at = u64::from_be_bytes(expire_at_bytes.try_into().unwrap());
+            return (value[TTL_HEADER_LEN..].to_vec(), Some(expire_at));
+        }
+    }
+    (value, None)
+}
+
+/// `true` once `expire_at` (a Unix timestamp) is no longer in the future.
+pub fn is_expired(expire_at: u64) -> bool {
+    now_unix() >= expire_at
+}
+
+/// Seconds remaining until `expire_at`, or `0` if it's already passed.
+pub fn remaining_secs(expire_at: u64) -> u64 {
+    expire_at.saturating_sub(now_unix())
+}
@@ -0,0 +1,172 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A retrying transaction client built on top of [`Storage::sched_txn_command`].
+//!
+//! Driving a transaction by hand means building a `Prewrite`, waiting on its callback, building
+//! a `Commit`, and coping with `WriteConflict`/`KeyIsLocked` by retrying the whole thing with a
+//! fresh start timestamp -- see `test_txn` for what that looks like today. [`TxnClient`] does
+//! this once: [`commit_mutations`](TxnClient::commit_mutations) gets a start timestamp from PD,
+//! prewrites, and on a transient conflict backs off, re-reads the affected keys, gets a fresh
+//! start timestamp, and retries the prewrite -- up to [`TxnClient::max_retries`] times -- before
+//! committing and returning the final [`TxnStatus`]. This is the same shape as a blockchain
+//! client that refreshes its blockhash and re-signs rather than failing outright on a stale one.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures03::compat::Future01CompatExt;
+use futures03::executor::block_on;
+use kvproto::kvrpcpb::Context;
+use pd_client::PdClient;
+use tikv_util::timer::GLOBAL_TIMER_HANDLE;
+use txn_types::{Key, Mutation, TimeStamp, TxnStatus};
+
+use crate::storage::kv::Engine;
+use crate::storage::lock_manager::LockManager;
+use crate::storage::mvcc::{Error as MvccError, ErrorInner as MvccErrorInner};
+use crate::storage::txn::{commands, Error as TxnError, ErrorInner as TxnErrorInner};
+use crate::storage::{Error, ErrorInner, Result, Storage};
+
+/// Default number of transient-conflict retries [`TxnClient::commit_mutations`] allows before
+/// surfacing the conflict to the caller. Would move to `Config` once this tree has a
+/// `storage::config` module entry for it (see `ReadPoolTuner`'s equivalent note).
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Base delay of the retry backoff; attempt `n` (1-indexed) waits `n * BACKOFF_BASE`.
+const BACKOFF_BASE: Duration = Duration::from_millis(10);
+
+/// `true` for exactly the errors [`TxnClient::commit_mutations`] treats as worth retrying with a
+/// fresh start timestamp: `WriteConflict` and `KeyIsLocked`. Anything else (e.g. a key too
+/// large, or the scheduler being too busy) is returned to the caller immediately.
+fn is_transient_conflict(err: &Error) -> bool {
+    matches!(
+        err,
+        Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::Mvcc(MvccError(
+            box MvccErrorInner::WriteConflict { .. },
+        ))))) | Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::Mvcc(MvccError(
+            box MvccErrorInner::KeyIsLocked(..),
+        )))))
+    )
+}
+
+/// A `commit_mutations` client wrapping [`Storage::sched_txn_command`] with automatic retry on
+/// a transient `WriteConflict`/`KeyIsLocked`.
+pub struct TxnClient<E: Engine, L: LockManager, P: PdClient> {
+    storage: Storage<E, L>,
+    pd_client: Arc<P>,
+    max_retries: u32,
+}
+
+impl<E: Engine, L: LockManager, P: PdClient> TxnClient<E, L, P> {
+    pub fn new(storage: Storage<E, L>, pd_client: Arc<P>) -> Self {
+        TxnClient {
+            storage,
+            pd_client,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Blocking variant of [`commit_mutations`](Self::commit_mutations), for callers not
+    /// already on an async runtime.
+    pub fn commit_mutations_sync(
+        &self,
+        ctx: Context,
+        mutations: Vec<Mutation>,
+        primary: Vec<u8>,
+    ) -> Result<TxnStatus> {
+        block_on(self.commit_mutations(ctx, mutations, primary))
+    }
+
+    /// Prewrites `mutations` (primary key `primary`) and commits them, retrying with a fresh
+    /// start timestamp on a transient `WriteConflict`/`KeyIsLocked` up to `max_retries` times.
+    /// Returns the committed [`TxnStatus`], or the terminal error once retries are exhausted.
+    pub async fn commit_mutations(
+        &self,
+        ctx: Context,
+        mutations: Vec<Mutation>,
+        primary: Vec<u8>,
+    ) -> Result<TxnStatus> {
+        let mut attempt = 0;
+        loop {
+            let start_ts = self.get_ts().await?;
+
+            match self
+                .prewrite_once(ctx.clone(), mutations.clone(), primary.clone(), start_ts)
+                .await
+            {
+                Ok(()) => {
+                    let keys: Vec<Key> = mutations.iter().map(|m| m.key().clone()).collect();
+                    let commit_ts = self.get_ts().await?;
+                    return self.commit_once(ctx, keys, start_ts, commit_ts).await;
+                }
+                Err(e) if attempt < self.max_retries && is_transient_conflict(&e) => {
+                    attempt += 1;
+                    self.backoff(attempt).await;
+                    // Refresh this client's view of the contended keys before replaying the
+                    // prewrite under a fresh start timestamp, same as re-reading an account's
+                    // balance before re-signing a transaction against it.
+                    for m in &mutations {
+                        let _ = self.storage.get(ctx.clone(), m.key().clone(), start_ts).await;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn get_ts(&self) -> Result<TimeStamp> {
+        self.pd_client
+            .get_tso()
+            .await
+            .map_err(|e| box_err!("failed to get timestamp from PD: {:?}", e))
+    }
+
+    async fn prewrite_once(
+        &self,
+        ctx: Context,
+        mutations: Vec<Mutation>,
+        primary: Vec<u8>,
+        start_ts: TimeStamp,
+    ) -> Result<()> {
+        let (tx, rx) = futures03::channel::oneshot::channel();
+        self.storage.sched_txn_command(
+            commands::Prewrite::with_context(mutations, primary, start_ts, ctx),
+            Box::new(move |res| {
+                let _ = tx.send(res);
+            }),
+        )?;
+        rx.await
+            .map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+    }
+
+    async fn commit_once(
+        &self,
+        ctx: Context,
+        keys: Vec<Key>,
+        lock_ts: TimeStamp,
+        commit_ts: TimeStamp,
+    ) -> Result<TxnStatus> {
+        let (tx, rx) = futures03::channel::oneshot::channel();
+        self.storage.sched_txn_command(
+            commands::Commit::new(keys, lock_ts, commit_ts, ctx),
+            Box::new(move |res| {
+                let _ = tx.send(res);
+            }),
+        )?;
+        rx.await
+            .map_err(|_| Error::from(ErrorInner::SchedTooBusy))?
+    }
+
+    async fn backoff(&self, attempt: u32) {
+        let delay = BACKOFF_BASE * attempt;
+        let _ = GLOBAL_TIMER_HANDLE
+            .delay(std::time::Instant::now() + delay)
+            .compat()
+            .await;
+    }
+}